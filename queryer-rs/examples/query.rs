@@ -1,4 +1,5 @@
 use anyhow::Result;
+use bytes::Bytes;
 use queryer_rs::{fetcher::Fetch, filetype, query};
 
 #[tokio::main]
@@ -33,7 +34,7 @@ struct CustomFetcher();
 impl Fetch for CustomFetcher {
     type Error = anyhow::Error;
 
-    async fn fetch(&self) -> Result<(filetype::Filetype, String), Self::Error> {
+    async fn fetch(&self) -> Result<(filetype::Filetype, Bytes), Self::Error> {
         Ok((
             filetype::Filetype::Json,
             r#"
@@ -42,7 +43,7 @@ impl Fetch for CustomFetcher {
                         {"name": "bb", "score": 91}
                     ]
                "#
-            .to_string(),
+            .into(),
         ))
     }
 }
@@ -1,22 +1,68 @@
 use anyhow::{anyhow, Result};
 use polars::prelude::*;
 use sqlparser::ast::{
-    BinaryOperator as SqlBinaryOperator, Expr as SqlExpr, Offset as SqlOffset, OrderByExpr, Select,
-    SelectItem, SetExpr, Statement, TableFactor, TableWithJoins, Value as SqlValue,
+    BinaryOperator as SqlBinaryOperator, Expr as SqlExpr, Function, FunctionArg, Ident,
+    JoinOperator, Offset as SqlOffset, OrderByExpr, Select, SelectItem, SetExpr, Statement,
+    TableFactor, TableWithJoins, Value as SqlValue,
 };
 
 pub struct Sql<'a> {
     pub(crate) selection: Vec<Expr>,
     pub(crate) condition: Option<Expr>,
     pub(crate) source: &'a str,
+    pub(crate) args: SourceArgs,
+    pub(crate) unnest: Vec<Unnest>,
+    /// keys of `GROUP BY`, the aggregates are in `selection`
+    pub(crate) group_by: Vec<Expr>,
+    /// condition on the groups, may compute aggregates of its own
+    pub(crate) having: Option<Expr>,
     pub(crate) order_by: Vec<(String, bool)>,
     pub(crate) offset: Option<i64>,
     pub(crate) limit: Option<usize>,
+    pub(crate) pushdown: Pushdown<'a>,
+}
+
+/// Parts of the query a source may evaluate itself to read less data.
+/// The query is still evaluated in full afterwards, so a source is free to ignore them,
+/// except databases: a where clause they evaluate as a whole is not applied again.
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct Pushdown<'a> {
+    /// columns referenced by the query, `None` if all of them are needed
+    pub(crate) columns: Option<Vec<String>>,
+    pub(crate) condition: Option<&'a SqlExpr>,
+    /// rows needed (offset + limit) once `condition` is applied, `None` with ORDER BY.
+    /// Must only be used by sources that evaluate the whole `condition`.
+    pub(crate) limit: Option<usize>,
+}
+
+/// Arguments given to the source in FROM, as for a table function
+/// `read_api('https://...', pagination => 'page')`
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct SourceArgs {
+    pub(crate) positional: Vec<String>,
+    pub(crate) named: Vec<(String, String)>,
+}
+
+impl SourceArgs {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.positional.is_empty() && self.named.is_empty()
+    }
+}
+
+/// A row per item of a list column, from `CROSS JOIN UNNEST(column) AS alias`
+/// or the `explode(source, column)` table function
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct Unnest {
+    pub(crate) column: String,
+    /// name of the item column, the list column itself is replaced without one
+    pub(crate) alias: Option<String>,
 }
 
 pub struct Expression(pub(crate) Box<SqlExpr>);
 pub struct Operation(pub(crate) SqlBinaryOperator);
 pub struct Projection<'a>(pub(crate) &'a SelectItem);
+pub struct Aggregate<'a>(pub(crate) &'a Function);
+pub struct Having<'a>(pub(crate) &'a SqlExpr);
 pub struct Source<'a>(pub(crate) &'a [TableWithJoins]);
 pub struct Order<'a>(pub(crate) &'a OrderByExpr);
 pub struct Offset<'a>(pub(crate) &'a SqlOffset);
@@ -36,15 +82,15 @@ impl<'a> TryFrom<&'a Statement> for Sql<'a> {
                     from: table_with_joins,
                     selection: where_clause,
                     projection,
-
-                    group_by: _,
+                    group_by,
+                    having: having_clause,
                     ..
                 } = match &q.body {
                     SetExpr::Select(statement) => statement.as_ref(),
                     _ => return Err(anyhow!("We only support Select Query at the moment")),
                 };
 
-                let source = Source(table_with_joins).try_into()?;
+                let (source, args, unnest) = Source(table_with_joins).try_into()?;
 
                 let condition = match where_clause {
                     Some(expr) => Some(Expression(Box::new(expr.to_owned())).try_into()?),
@@ -57,6 +103,19 @@ impl<'a> TryFrom<&'a Statement> for Sql<'a> {
                     selection.push(expr);
                 }
 
+                let mut keys = Vec::with_capacity(group_by.len());
+                for expr in group_by {
+                    keys.push(Expression(Box::new(expr.to_owned())).try_into()?);
+                }
+
+                let having = match having_clause {
+                    Some(_) if group_by.is_empty() => {
+                        return Err(anyhow!("HAVING is only supported with GROUP BY"))
+                    }
+                    Some(expr) => Some(Having(expr).try_into()?),
+                    None => None,
+                };
+
                 let mut order_by = Vec::new();
                 for expr in orders {
                     order_by.push(Order(expr).try_into()?);
@@ -65,13 +124,39 @@ impl<'a> TryFrom<&'a Statement> for Sql<'a> {
                 let offset = offset.map(|v| Offset(v).into());
                 let limit = limit.map(|v| Limit(v).into());
 
+                // unnested rows and columns only exist once the data is loaded
+                let pushdown = match unnest.is_empty() {
+                    true => Pushdown {
+                        columns: referenced_columns(
+                            projection,
+                            where_clause.as_ref(),
+                            group_by,
+                            having_clause.as_ref(),
+                            &order_by,
+                        ),
+                        condition: where_clause.as_ref(),
+                        limit: match order_by.is_empty() && group_by.is_empty() {
+                            true => {
+                                limit.map(|l: usize| l.saturating_add(offset.unwrap_or(0) as usize))
+                            }
+                            false => None,
+                        },
+                    },
+                    false => Pushdown::default(),
+                };
+
                 Ok(Sql {
                     selection,
                     condition,
                     source,
+                    args,
+                    unnest,
+                    group_by: keys,
+                    having,
                     order_by,
                     offset,
                     limit,
+                    pushdown,
                 })
             }
             _ => Err(anyhow!("We only support Query at the moment")),
@@ -79,6 +164,58 @@ impl<'a> TryFrom<&'a Statement> for Sql<'a> {
     }
 }
 
+/// Columns used by projection, where clause, group by, having and order by, `None` when
+/// all columns are needed or the query uses expressions we can not see through
+fn referenced_columns(
+    projection: &[SelectItem],
+    condition: Option<&SqlExpr>,
+    group_by: &[SqlExpr],
+    having: Option<&SqlExpr>,
+    order_by: &[(String, bool)],
+) -> Option<Vec<String>> {
+    fn collect(expr: &SqlExpr, columns: &mut Vec<String>) -> Option<()> {
+        match expr {
+            // may be a nested field, which is only known once the data is loaded
+            SqlExpr::Identifier(id) if id.value.contains('.') => None,
+            SqlExpr::Identifier(id) => {
+                if !columns.contains(&id.value) {
+                    columns.push(id.value.clone());
+                }
+                Some(())
+            }
+            SqlExpr::Value(_) => Some(()),
+            SqlExpr::BinaryOp { left, right, .. } => {
+                collect(left, columns)?;
+                collect(right, columns)
+            }
+            SqlExpr::Nested(e)
+            | SqlExpr::IsNull(e)
+            | SqlExpr::IsNotNull(e)
+            | SqlExpr::UnaryOp { expr: e, .. } => collect(e, columns),
+            _ => None,
+        }
+    }
+
+    let mut columns = Vec::new();
+    for item in projection {
+        match item {
+            SelectItem::UnnamedExpr(expr) | SelectItem::ExprWithAlias { expr, .. } => {
+                collect(expr, &mut columns)?
+            }
+            _ => return None,
+        }
+    }
+    for expr in condition.into_iter().chain(group_by).chain(having) {
+        collect(expr, &mut columns)?;
+    }
+    for (name, _) in order_by {
+        if !columns.contains(name) {
+            columns.push(name.clone());
+        }
+    }
+    Some(columns)
+}
+
 impl TryFrom<Expression> for Expr {
     type Error = anyhow::Error;
 
@@ -91,6 +228,8 @@ impl TryFrom<Expression> for Expr {
             }),
             SqlExpr::Wildcard => Ok(Self::Wildcard),
             SqlExpr::Identifier(id) => Ok(Self::Column(Arc::from(id.value))),
+            SqlExpr::CompoundIdentifier(ids) => Ok(col(&dotted(&ids))),
+            SqlExpr::Nested(e) => Expression(e).try_into(),
             SqlExpr::Value(v) => Ok(Self::Literal(Value(v).try_into()?)),
             v => Err(anyhow!("expr {:#?} is not supported", v)),
         }
@@ -127,6 +266,7 @@ impl<'a> TryFrom<Projection<'a>> for Expr {
     fn try_from(p: Projection<'a>) -> Result<Self, Self::Error> {
         match p.0 {
             SelectItem::UnnamedExpr(SqlExpr::Identifier(id)) => Ok(col(&id.to_string())),
+            SelectItem::UnnamedExpr(SqlExpr::CompoundIdentifier(ids)) => Ok(col(&dotted(ids))),
             SelectItem::ExprWithAlias {
                 expr: SqlExpr::Identifier(id),
                 alias,
@@ -134,6 +274,15 @@ impl<'a> TryFrom<Projection<'a>> for Expr {
                 Box::new(Expr::Column(Arc::from(id.to_string()))),
                 Arc::from(alias.to_string()),
             )),
+            SelectItem::ExprWithAlias {
+                expr: SqlExpr::CompoundIdentifier(ids),
+                alias,
+            } => Ok(col(&dotted(ids)).alias(&alias.value)),
+            SelectItem::UnnamedExpr(SqlExpr::Function(f)) => Aggregate(f).try_into(),
+            SelectItem::ExprWithAlias {
+                expr: SqlExpr::Function(f),
+                alias,
+            } => Ok(Expr::try_from(Aggregate(f))?.alias(&alias.value)),
             SelectItem::QualifiedWildcard(v) => Ok(col(&v.to_string())),
             SelectItem::Wildcard => Ok(col("*")),
             item => Err(anyhow!("projection {} not supported", item)),
@@ -141,7 +290,102 @@ impl<'a> TryFrom<Projection<'a>> for Expr {
     }
 }
 
-impl<'a> TryFrom<Source<'a>> for &'a str {
+/// `count(*)`, `count([distinct] a)`, `sum(a)`, `avg(a)`, `min(a)` and `max(a)`,
+/// the column is named after the function unless an alias is given
+impl<'a> TryFrom<Aggregate<'a>> for Expr {
+    type Error = anyhow::Error;
+
+    fn try_from(f: Aggregate<'a>) -> Result<Self, Self::Error> {
+        let name = f.0.name.to_string().to_lowercase();
+        let arg = match f.0.args.as_slice() {
+            _ if f.0.over.is_some() => return Err(anyhow!("window functions are not supported")),
+            [FunctionArg::Unnamed(SqlExpr::Wildcard)] if name == "count" && !f.0.distinct => {
+                return Ok(count().alias(&name))
+            }
+            [FunctionArg::Unnamed(arg)] => Expr::try_from(Expression(Box::new(arg.clone())))?,
+            _ => return Err(anyhow!("function {} is not supported", f.0)),
+        };
+        let expr = match (name.as_str(), f.0.distinct) {
+            ("count", true) => arg.drop_nulls().n_unique(),
+            ("count", false) => arg.drop_nulls().count(),
+            ("sum", false) => arg.sum(),
+            ("avg", false) => arg.mean(),
+            ("min", false) => arg.min(),
+            ("max", false) => arg.max(),
+            _ => return Err(anyhow!("function {} is not supported", f.0)),
+        };
+        Ok(expr.alias(&name))
+    }
+}
+
+/// Like a where clause, except that functions are aggregates of the group
+impl<'a> TryFrom<Having<'a>> for Expr {
+    type Error = anyhow::Error;
+
+    fn try_from(h: Having<'a>) -> Result<Self, Self::Error> {
+        match h.0 {
+            SqlExpr::Function(f) => Aggregate(f).try_into(),
+            SqlExpr::BinaryOp { left, op, right } => Ok(Expr::BinaryExpr {
+                left: Box::new(Having(left).try_into()?),
+                op: Operation(op.clone()).try_into()?,
+                right: Box::new(Having(right).try_into()?),
+            }),
+            SqlExpr::Nested(e) => Having(e).try_into(),
+            e => Expression(Box::new(e.clone())).try_into(),
+        }
+    }
+}
+
+fn dotted(ids: &[Ident]) -> String {
+    ids.iter()
+        .map(|id| id.value.as_str())
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Replace columns like `address.city` or `tags.0` that are not in `schema` by
+/// the access to a field of a struct column or an item of a list column
+pub(crate) fn resolve_nested(mut expr: Expr, schema: &Schema) -> Expr {
+    expr.mutate().apply(|e| {
+        if let Expr::Column(name) = e {
+            if schema.get(name).is_none() {
+                if let Some(nested) = nested_column(name, schema) {
+                    *e = nested;
+                }
+            }
+        }
+        true
+    });
+    expr
+}
+
+fn nested_column(name: &str, schema: &Schema) -> Option<Expr> {
+    let parts: Vec<_> = name.split('.').collect();
+    // the longest prefix naming a column, column names may contain dots as well
+    let (n, dtype) = (1..parts.len())
+        .rev()
+        .find_map(|n| schema.get(&parts[..n].join(".")).map(|dtype| (n, dtype)))?;
+
+    let mut expr = col(&parts[..n].join("."));
+    let mut dtype = dtype.clone();
+    for part in &parts[n..] {
+        match dtype {
+            DataType::Struct(fields) => {
+                let field = fields.into_iter().find(|f| f.name() == part)?;
+                expr = expr.struct_().field_by_name(part);
+                dtype = field.data_type().clone();
+            }
+            DataType::List(inner) => {
+                expr = expr.arr().get(lit(part.parse::<i64>().ok()?));
+                dtype = *inner;
+            }
+            _ => return None,
+        }
+    }
+    Some(expr.alias(name))
+}
+
+impl<'a> TryFrom<Source<'a>> for (&'a str, SourceArgs, Vec<Unnest>) {
     type Error = anyhow::Error;
 
     fn try_from(source: Source<'a>) -> Result<Self, Self::Error> {
@@ -150,14 +394,81 @@ impl<'a> TryFrom<Source<'a>> for &'a str {
         }
 
         let table = &source.0[0];
-        if !table.joins.is_empty() {
-            return Err(anyhow!("We do not support joint data source at the moment"));
+        let mut unnest = Vec::new();
+        for join in &table.joins {
+            match (&join.relation, &join.join_operator) {
+                (
+                    TableFactor::Table {
+                        name, args, alias, ..
+                    },
+                    JoinOperator::CrossJoin,
+                ) if name.to_string().eq_ignore_ascii_case("unnest") => {
+                    let column = match args.as_slice() {
+                        [FunctionArg::Unnamed(expr)] => column_name(expr)?,
+                        _ => return Err(anyhow!("UNNEST takes a single column")),
+                    };
+                    unnest.push(Unnest {
+                        column,
+                        alias: alias.as_ref().map(|a| a.name.value.clone()),
+                    });
+                }
+                _ => {
+                    return Err(anyhow!(
+                        "We only support CROSS JOIN UNNEST(column) at the moment"
+                    ))
+                }
+            }
         }
 
-        match &table.relation {
-            TableFactor::Table { name, .. } => Ok(&name.0.first().unwrap().value),
-            _ => Err(anyhow!("We only support table")),
+        let (name, args) = match &table.relation {
+            TableFactor::Table { name, args, .. } => (&name.0.first().unwrap().value, args),
+            _ => return Err(anyhow!("We only support table")),
+        };
+
+        // explode(source, column, ...) reads the source given as first argument
+        let exploding = name.eq_ignore_ascii_case("explode");
+        let (name, args) = match args.split_first() {
+            Some((FunctionArg::Unnamed(source), rest)) if exploding => match source {
+                SqlExpr::Identifier(id) => (&id.value, rest),
+                SqlExpr::Value(SqlValue::SingleQuotedString(v)) => (v, rest),
+                v => return Err(anyhow!("explode expects a source, got {}", v)),
+            },
+            _ if exploding => return Err(anyhow!("explode expects a source and columns")),
+            _ => (name, args.as_slice()),
+        };
+
+        let mut source_args = SourceArgs::default();
+        for arg in args {
+            match arg {
+                FunctionArg::Named { name, arg } => source_args
+                    .named
+                    .push((name.value.clone(), literal_string(arg)?)),
+                FunctionArg::Unnamed(arg) if exploding => unnest.push(Unnest {
+                    column: column_name(arg)?,
+                    alias: None,
+                }),
+                FunctionArg::Unnamed(arg) => source_args.positional.push(literal_string(arg)?),
+            }
         }
+        Ok((name, source_args, unnest))
+    }
+}
+
+fn column_name(expr: &SqlExpr) -> Result<String> {
+    match expr {
+        SqlExpr::Identifier(id) => Ok(id.value.clone()),
+        SqlExpr::CompoundIdentifier(ids) => Ok(dotted(ids)),
+        v => Err(anyhow!("expect a column, got {}", v)),
+    }
+}
+
+fn literal_string(expr: &SqlExpr) -> Result<String> {
+    match expr {
+        SqlExpr::Value(SqlValue::SingleQuotedString(v)) => Ok(v.clone()),
+        SqlExpr::Value(SqlValue::Number(v, _)) => Ok(v.clone()),
+        SqlExpr::Value(SqlValue::Boolean(v)) => Ok(v.to_string()),
+        SqlExpr::Identifier(id) => Ok(id.value.clone()),
+        v => Err(anyhow!("source argument {} must be a literal", v)),
     }
 }
 
@@ -205,6 +516,7 @@ impl TryFrom<Value> for LiteralValue {
     fn try_from(v: Value) -> Result<Self, Self::Error> {
         match v.0 {
             SqlValue::Number(v, _) => Ok(LiteralValue::Float64(v.parse().unwrap())),
+            SqlValue::SingleQuotedString(v) => Ok(LiteralValue::Utf8(v)),
             SqlValue::Boolean(v) => Ok(LiteralValue::Boolean(v)),
             SqlValue::Null => Ok(LiteralValue::Null),
             v => Err(anyhow!("Value {} is not supported", v)),
@@ -232,5 +544,171 @@ mod tests {
         assert_eq!(sql.offset, Some(10));
         assert_eq!(sql.order_by, vec![("c".into(), true)]);
         assert_eq!(sql.selection, vec![col("a"), col("b"), col("c")]);
+        // no limit pushdown with order by
+        assert_eq!(sql.pushdown.limit, None);
+    }
+
+    #[test]
+    fn parse_sql_pushdown_works() {
+        let sql = "select a, b x from file://a.csv where c = 'v' limit 5 offset 10";
+        let statement = &Parser::parse_sql(&SqlDialect, sql).unwrap()[0];
+        let sql: Sql = statement.try_into().unwrap();
+        assert_eq!(
+            sql.pushdown.columns,
+            Some(vec!["a".to_string(), "b".into(), "c".into()])
+        );
+        assert_eq!(sql.pushdown.limit, Some(15));
+
+        // the limit applies to the groups
+        let sql = "select a from file://a.csv group by a, b limit 5";
+        let statement = &Parser::parse_sql(&SqlDialect, sql).unwrap()[0];
+        let sql: Sql = statement.try_into().unwrap();
+        assert_eq!(
+            sql.pushdown.columns,
+            Some(vec!["a".to_string(), "b".into()])
+        );
+        assert_eq!(sql.pushdown.limit, None);
+
+        let sql = "select * from file://a.csv";
+        let statement = &Parser::parse_sql(&SqlDialect, sql).unwrap()[0];
+        let sql: Sql = statement.try_into().unwrap();
+        assert_eq!(sql.pushdown, Pushdown::default());
+    }
+
+    #[test]
+    fn parse_sql_with_fragment_source_works() {
+        let url = "file://./data.zip#inner.csv";
+        let sql = format!("select a from {} where a > 1", url);
+        let statement = &Parser::parse_sql(&SqlDialect, sql.as_ref()).unwrap()[0];
+        let sql: Sql = statement.try_into().unwrap();
+        assert_eq!(sql.source, url);
+    }
+
+    #[test]
+    fn parse_sql_with_quoted_source_works() {
+        let url = "data:text/csv;base64,YSxiCjEsMgo=";
+        let sql = format!("select a from '{}'", url);
+        let statement = &Parser::parse_sql(&SqlDialect, sql.as_ref()).unwrap()[0];
+        let sql: Sql = statement.try_into().unwrap();
+        assert_eq!(sql.source, url);
+    }
+
+    #[test]
+    fn parse_sql_with_source_args_works() {
+        let sql = "select a from read_api('https://api.xyz/items', pagination => 'cursor:next', max_pages => 5)";
+        let statement = &Parser::parse_sql(&SqlDialect, sql).unwrap()[0];
+        let sql: Sql = statement.try_into().unwrap();
+        assert_eq!(sql.source, "read_api");
+        assert_eq!(sql.args.positional, vec!["https://api.xyz/items"]);
+        assert_eq!(
+            sql.args.named,
+            vec![
+                ("pagination".to_string(), "cursor:next".to_string()),
+                ("max_pages".into(), "5".into())
+            ]
+        );
+    }
+
+    #[test]
+    fn resolve_nested_should_access_fields() {
+        let df = df! {
+            "id" => [1, 2],
+            "a.b" => [3, 4],
+        }
+        .unwrap();
+        let address = StructChunked::new("address", &[Series::new("city", ["x", "y"])])
+            .unwrap()
+            .into_series();
+        let tags = Series::new(
+            "tags",
+            [Series::new("", [10, 11]), Series::new("", [20, 21])],
+        );
+        let df = df.hstack(&[address, tags]).unwrap();
+        let schema = df.schema();
+
+        let exprs = [col("address.city"), col("tags.1"), col("a.b"), col("id")]
+            .into_iter()
+            .map(|e| resolve_nested(e, &schema))
+            .collect::<Vec<_>>();
+        let out = df
+            .lazy()
+            .filter(resolve_nested(col("tags.0").gt(lit(15)), &schema))
+            .select(exprs)
+            .collect()
+            .unwrap();
+        assert_eq!(
+            vec!["address.city", "tags.1", "a.b", "id"],
+            out.get_column_names()
+        );
+        assert_eq!(
+            Some("y"),
+            out.column("address.city").unwrap().utf8().unwrap().get(0)
+        );
+        assert_eq!(
+            Some(21),
+            out.column("tags.1").unwrap().i32().unwrap().get(0)
+        );
+
+        // unknown fields are left to fail as missing columns
+        assert_eq!(
+            col("address.zip"),
+            resolve_nested(col("address.zip"), &schema)
+        );
+    }
+
+    #[test]
+    fn parse_sql_with_unnest_works() {
+        let sql =
+            "select user, r from file://a.json cross join unnest(roles) as r where r = 'x' limit 1";
+        let statement = &Parser::parse_sql(&SqlDialect, sql).unwrap()[0];
+        let sql: Sql = statement.try_into().unwrap();
+        assert_eq!(sql.source, "file://a.json");
+        assert_eq!(
+            sql.unnest,
+            vec![Unnest {
+                column: "roles".into(),
+                alias: Some("r".into())
+            }]
+        );
+        assert_eq!(sql.pushdown, Pushdown::default());
+
+        let sql = "select * from explode(file://a.json, roles, tags, root => '/data')";
+        let statement = &Parser::parse_sql(&SqlDialect, sql).unwrap()[0];
+        let sql: Sql = statement.try_into().unwrap();
+        assert_eq!(sql.source, "file://a.json");
+        assert_eq!(sql.unnest.len(), 2);
+        assert_eq!(
+            sql.args.named,
+            vec![("root".to_string(), "/data".to_string())]
+        );
+
+        let sql = "select * from a join b on a.id = b.id";
+        let statement = &Parser::parse_sql(&SqlDialect, sql).unwrap()[0];
+        assert!(Sql::try_from(statement).is_err());
+    }
+
+    #[tokio::test]
+    async fn query_unnest_should_drop_empty_lists() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("users.json");
+        std::fs::write(
+            &path,
+            r#"[{"user": "a", "roles": ["x", "y"]}, {"user": "b", "roles": []}, {"user": "c", "roles": ["z"]}]"#,
+        )
+        .unwrap();
+
+        let sql = format!(
+            "select user, r from file://{} cross join unnest(roles) as r",
+            path.display()
+        );
+        let ds = crate::query(sql).await.unwrap();
+        let users: Vec<_> = ds
+            .column("user")
+            .unwrap()
+            .utf8()
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(vec![Some("a"), Some("a"), Some("c")], users);
     }
 }
@@ -0,0 +1,186 @@
+use std::borrow::Cow;
+
+use anyhow::Result;
+use polars::prelude::*;
+
+/// Type of a column as declared by the source, e.g. a database or a log format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Kind {
+    /// not declared, inferred from the first value
+    Unknown,
+    Int,
+    Float,
+    Bool,
+    Text,
+    /// read as text and parsed when the column is complete
+    Date,
+    Datetime,
+}
+
+/// A single value of a result row
+#[derive(Debug, PartialEq)]
+pub(crate) enum Cell<'a> {
+    Null,
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Text(Cow<'a, str>),
+}
+
+#[derive(Debug)]
+enum Values {
+    Unknown(usize),
+    Int(Vec<Option<i64>>),
+    Float(Vec<Option<f64>>),
+    Bool(Vec<Option<bool>>),
+    Text(Vec<Option<String>>),
+}
+
+/// Collect the values of a result column into a polars series
+#[derive(Debug)]
+pub(crate) struct ColumnBuilder {
+    name: String,
+    values: Values,
+    temporal: Option<Kind>,
+}
+
+impl ColumnBuilder {
+    pub(crate) fn new(name: &str, kind: Kind) -> Self {
+        let values = match kind {
+            Kind::Unknown => Values::Unknown(0),
+            Kind::Int => Values::Int(Vec::new()),
+            Kind::Float => Values::Float(Vec::new()),
+            Kind::Bool => Values::Bool(Vec::new()),
+            Kind::Text | Kind::Date | Kind::Datetime => Values::Text(Vec::new()),
+        };
+        let temporal = matches!(kind, Kind::Date | Kind::Datetime).then_some(kind);
+
+        Self {
+            name: name.to_string(),
+            values,
+            temporal,
+        }
+    }
+
+    pub(crate) fn push(&mut self, cell: Cell) {
+        match (&mut self.values, cell) {
+            (Values::Unknown(n), Cell::Null) => *n += 1,
+            (Values::Unknown(n), cell) => {
+                let nulls = *n;
+                self.values = match cell {
+                    Cell::Int(_) => Values::Int(vec![None; nulls]),
+                    Cell::Float(_) => Values::Float(vec![None; nulls]),
+                    Cell::Bool(_) => Values::Bool(vec![None; nulls]),
+                    _ => Values::Text(vec![None; nulls]),
+                };
+                self.push(cell);
+            }
+            (Values::Int(vs), Cell::Int(i)) => vs.push(Some(i)),
+            (Values::Int(vs), Cell::Null) => vs.push(None),
+            (Values::Float(vs), Cell::Float(f)) => vs.push(Some(f)),
+            (Values::Float(vs), Cell::Int(i)) => vs.push(Some(i as f64)),
+            (Values::Float(vs), Cell::Null) => vs.push(None),
+            (Values::Bool(vs), Cell::Bool(b)) => vs.push(Some(b)),
+            (Values::Bool(vs), Cell::Int(i)) => vs.push(Some(i != 0)),
+            (Values::Bool(vs), Cell::Null) => vs.push(None),
+            (Values::Text(vs), Cell::Null) => vs.push(None),
+            (Values::Text(vs), Cell::Text(t)) => vs.push(Some(t.into_owned())),
+            (Values::Text(vs), cell) => vs.push(Some(cell.to_string())),
+            // an integer column holding a real
+            (Values::Int(vs), Cell::Float(f)) => {
+                let mut floats: Vec<_> = vs.iter().map(|v| v.map(|i| i as f64)).collect();
+                floats.push(Some(f));
+                self.values = Values::Float(floats);
+            }
+            // the value does not match the declared type, fall back to text
+            (_, cell) => {
+                let mut texts = self.texts();
+                texts.push(Some(cell.to_string()));
+                self.values = Values::Text(texts);
+                self.temporal = None;
+            }
+        }
+    }
+
+    fn texts(&self) -> Vec<Option<String>> {
+        match &self.values {
+            Values::Unknown(n) => vec![None; *n],
+            Values::Int(vs) => vs.iter().map(|v| v.map(|v| v.to_string())).collect(),
+            Values::Float(vs) => vs.iter().map(|v| v.map(|v| v.to_string())).collect(),
+            Values::Bool(vs) => vs.iter().map(|v| v.map(|v| v.to_string())).collect(),
+            Values::Text(vs) => vs.clone(),
+        }
+    }
+
+    pub(crate) fn finish(self) -> Result<Series> {
+        let name = self.name.as_str();
+        let series = match self.values {
+            Values::Unknown(n) => Series::full_null(name, n, &DataType::Utf8),
+            Values::Int(vs) => Series::new(name, vs),
+            Values::Float(vs) => Series::new(name, vs),
+            Values::Bool(vs) => Series::new(name, vs),
+            Values::Text(vs) => {
+                let ca = Utf8Chunked::from_iter_options(name, vs.into_iter());
+                // keep the text if it does not parse
+                match self.temporal {
+                    Some(Kind::Date) => ca
+                        .as_date(None, false)
+                        .map(|d| d.into_series())
+                        .unwrap_or_else(|_| ca.into_series()),
+                    Some(_) => ca
+                        .as_datetime(None, TimeUnit::Milliseconds, false, false)
+                        .map(|d| d.into_series())
+                        .unwrap_or_else(|_| ca.into_series()),
+                    None => ca.into_series(),
+                }
+            }
+        };
+        Ok(series)
+    }
+}
+
+impl std::fmt::Display for Cell<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Cell::Null => Ok(()),
+            Cell::Int(i) => i.fmt(f),
+            Cell::Float(v) => v.fmt(f),
+            Cell::Bool(b) => b.fmt(f),
+            Cell::Text(t) => t.fmt(f),
+        }
+    }
+}
+
+/// Build a data frame from the result columns
+pub(crate) fn to_data_frame(columns: Vec<ColumnBuilder>) -> Result<DataFrame> {
+    let series = columns
+        .into_iter()
+        .map(ColumnBuilder::finish)
+        .collect::<Result<Vec<_>>>()?;
+    Ok(DataFrame::new(series)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn column_builder_should_widen_types() {
+        let mut c = ColumnBuilder::new("a", Kind::Unknown);
+        c.push(Cell::Null);
+        c.push(Cell::Int(1));
+        c.push(Cell::Float(1.5));
+        let s = c.finish().unwrap();
+        assert_eq!(&DataType::Float64, s.dtype());
+        assert_eq!(1, s.null_count());
+
+        let mut c = ColumnBuilder::new("b", Kind::Date);
+        c.push(Cell::Text("2023-01-02".into()));
+        assert_eq!(&DataType::Date, c.finish().unwrap().dtype());
+
+        let mut c = ColumnBuilder::new("c", Kind::Int);
+        c.push(Cell::Int(1));
+        c.push(Cell::Text("x".into()));
+        assert_eq!(&DataType::Utf8, c.finish().unwrap().dtype());
+    }
+}
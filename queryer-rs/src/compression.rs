@@ -0,0 +1,205 @@
+use std::{
+    io::{Cursor, Read},
+    pin::Pin,
+};
+
+use anyhow::{anyhow, Context, Result};
+use async_compression::tokio::bufread::{BzDecoder, GzipDecoder, XzDecoder, ZstdDecoder};
+use bytes::Bytes;
+use tokio::io::{AsyncBufRead, AsyncRead};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Compression {
+    Gzip,
+    Zstd,
+    Bzip2,
+    Xz,
+    Zip,
+}
+
+impl Compression {
+    pub(crate) fn from_extension(ext: &str) -> Option<Self> {
+        match ext.to_lowercase().as_str() {
+            "gz" | "gzip" => Some(Self::Gzip),
+            "zst" | "zstd" => Some(Self::Zstd),
+            "bz2" | "bzip2" => Some(Self::Bzip2),
+            "xz" => Some(Self::Xz),
+            "zip" => Some(Self::Zip),
+            _ => None,
+        }
+    }
+
+    /// map a `Content-Encoding` header value, `identity` and unknown encodings give `None`
+    pub(crate) fn from_content_encoding(encoding: &str) -> Option<Self> {
+        match encoding.trim().to_lowercase().as_str() {
+            "gzip" | "x-gzip" => Some(Self::Gzip),
+            "zstd" => Some(Self::Zstd),
+            "bzip2" | "x-bzip2" => Some(Self::Bzip2),
+            "xz" | "x-xz" => Some(Self::Xz),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn from_magic(data: &[u8]) -> Option<Self> {
+        match data {
+            [0x1f, 0x8b, ..] => Some(Self::Gzip),
+            [0x28, 0xb5, 0x2f, 0xfd, ..] => Some(Self::Zstd),
+            [b'B', b'Z', b'h', ..] => Some(Self::Bzip2),
+            [0xfd, b'7', b'z', b'X', b'Z', 0x00, ..] => Some(Self::Xz),
+            [b'P', b'K', 0x03, 0x04, ..] => Some(Self::Zip),
+            _ => None,
+        }
+    }
+}
+
+/// Wrap `reader` into a streaming decoder. Zip archives need random access,
+/// use [`decompress`] for them.
+pub(crate) fn decoder<'a, R: Read + 'a>(
+    compression: Compression,
+    reader: R,
+) -> Result<Box<dyn Read + 'a>> {
+    Ok(match compression {
+        Compression::Gzip => Box::new(flate2::read::MultiGzDecoder::new(reader)),
+        Compression::Zstd => Box::new(zstd::Decoder::new(reader)?),
+        Compression::Bzip2 => Box::new(bzip2::read::MultiBzDecoder::new(reader)),
+        Compression::Xz => Box::new(xz2::read::XzDecoder::new_multi_decoder(reader)),
+        Compression::Zip => return Err(anyhow!("zip archives can not be decoded as a stream")),
+    })
+}
+
+/// Like [`decoder`], for data that is still being downloaded
+pub(crate) fn async_decoder<'a, R: AsyncBufRead + Send + 'a>(
+    compression: Compression,
+    reader: R,
+) -> Result<Pin<Box<dyn AsyncRead + Send + 'a>>> {
+    Ok(match compression {
+        Compression::Gzip => {
+            let mut decoder = GzipDecoder::new(reader);
+            decoder.multiple_members(true);
+            Box::pin(decoder)
+        }
+        Compression::Zstd => {
+            let mut decoder = ZstdDecoder::new(reader);
+            decoder.multiple_members(true);
+            Box::pin(decoder)
+        }
+        Compression::Bzip2 => {
+            let mut decoder = BzDecoder::new(reader);
+            decoder.multiple_members(true);
+            Box::pin(decoder)
+        }
+        Compression::Xz => {
+            let mut decoder = XzDecoder::new(reader);
+            decoder.multiple_members(true);
+            Box::pin(decoder)
+        }
+        Compression::Zip => return Err(anyhow!("zip archives can not be decoded as a stream")),
+    })
+}
+
+/// Decompress `data`. For zip archives `entry` selects the file to extract, it can be
+/// omitted when the archive holds a single file. Returns the data and, for zip archives,
+/// the name of the extracted entry.
+pub(crate) fn decompress(
+    data: Bytes,
+    compression: Compression,
+    entry: Option<&str>,
+) -> Result<(Bytes, Option<String>)> {
+    let mut buf = Vec::new();
+    if compression != Compression::Zip {
+        decoder(compression, data.as_ref())?
+            .read_to_end(&mut buf)
+            .with_context(|| format!("failed to decompress {:?} data", compression))?;
+        return Ok((buf.into(), None));
+    }
+
+    let mut archive = zip::ZipArchive::new(Cursor::new(data))?;
+    let name = match entry {
+        Some(name) => name.to_string(),
+        None => {
+            let mut files = archive.file_names().filter(|name| !name.ends_with('/'));
+            match (files.next(), files.next()) {
+                (Some(name), None) => name.to_string(),
+                (None, _) => return Err(anyhow!("zip archive is empty")),
+                _ => {
+                    return Err(anyhow!(
+                        "zip archive contains several files, select one with `#<name>`"
+                    ))
+                }
+            }
+        }
+    };
+    archive
+        .by_name(&name)
+        .with_context(|| format!("{} not found in zip archive", name))?
+        .read_to_end(&mut buf)?;
+
+    Ok((buf.into(), Some(name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    const DATA: &[u8] = b"a,b\n1,2\n";
+
+    fn compress(compression: Compression) -> Vec<u8> {
+        match compression {
+            Compression::Gzip => {
+                let mut e = flate2::write::GzEncoder::new(Vec::new(), Default::default());
+                e.write_all(DATA).unwrap();
+                e.finish().unwrap()
+            }
+            Compression::Zstd => zstd::encode_all(DATA, 0).unwrap(),
+            Compression::Bzip2 => {
+                let mut e = bzip2::write::BzEncoder::new(Vec::new(), Default::default());
+                e.write_all(DATA).unwrap();
+                e.finish().unwrap()
+            }
+            Compression::Xz => {
+                let mut e = xz2::write::XzEncoder::new(Vec::new(), 6);
+                e.write_all(DATA).unwrap();
+                e.finish().unwrap()
+            }
+            Compression::Zip => {
+                let mut w = zip::ZipWriter::new(Cursor::new(Vec::new()));
+                w.start_file("inner.csv", Default::default()).unwrap();
+                w.write_all(DATA).unwrap();
+                w.start_file("other.json", Default::default()).unwrap();
+                w.write_all(b"[]").unwrap();
+                w.finish().unwrap().into_inner()
+            }
+        }
+    }
+
+    #[test]
+    fn decompress_should_work() {
+        for c in [
+            Compression::Gzip,
+            Compression::Zstd,
+            Compression::Bzip2,
+            Compression::Xz,
+        ] {
+            let data = compress(c);
+            assert_eq!(Some(c), Compression::from_magic(&data));
+            let (out, entry) = decompress(data.into(), c, None).unwrap();
+            assert_eq!(DATA, out.as_ref());
+            assert_eq!(None, entry);
+        }
+    }
+
+    #[test]
+    fn decompress_zip_should_pick_entry() {
+        let data: Bytes = compress(Compression::Zip).into();
+        assert_eq!(Some(Compression::Zip), Compression::from_magic(&data));
+
+        let (out, entry) = decompress(data.clone(), Compression::Zip, Some("inner.csv")).unwrap();
+        assert_eq!(DATA, out.as_ref());
+        assert_eq!(Some("inner.csv".to_string()), entry);
+
+        assert!(decompress(data.clone(), Compression::Zip, None).is_err());
+        assert!(decompress(data, Compression::Zip, Some("missing.csv")).is_err());
+    }
+}
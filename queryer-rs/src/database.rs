@@ -0,0 +1,249 @@
+use anyhow::{anyhow, Result};
+use sqlparser::ast::{BinaryOperator, Expr as SqlExpr, UnaryOperator, Value as SqlValue};
+
+use crate::{ast_convert::Pushdown, DataSet};
+
+use self::{mysql::MysqlSource, postgres::PostgresSource, sqlite::SqliteSource};
+
+mod mysql;
+mod postgres;
+mod sqlite;
+
+/// A table in a database, queried directly instead of being fetched and transformed
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum DatabaseSource {
+    Sqlite(SqliteSource),
+    Postgres(PostgresSource),
+    Mysql(MysqlSource),
+}
+
+impl DatabaseSource {
+    /// `None` if `source` is not a database
+    pub(crate) fn parse(source: &str) -> Result<Option<Self>> {
+        match source.split_once("://") {
+            Some(("sqlite", name)) => Ok(Some(Self::Sqlite(SqliteSource::parse(name)?))),
+            Some(("postgres" | "postgresql", _)) => {
+                Ok(Some(Self::Postgres(PostgresSource::parse(source)?)))
+            }
+            Some(("mysql", _)) => Ok(Some(Self::Mysql(MysqlSource::parse(source)?))),
+            _ => Ok(None),
+        }
+    }
+
+    pub(crate) async fn load(self, pushdown: &Pushdown<'_>) -> Result<DataSet> {
+        match self {
+            Self::Sqlite(db) => db.load(pushdown).await,
+            Self::Postgres(db) => db.load(pushdown).await,
+            Self::Mysql(db) => db.load(pushdown).await,
+        }
+    }
+}
+
+/// How identifiers and string literals are written in the database's SQL
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Dialect {
+    Ansi,
+    /// Backquoted identifiers, backslashes escape characters in strings
+    Mysql,
+}
+
+impl Dialect {
+    pub(crate) fn quote(self) -> char {
+        match self {
+            Self::Ansi => '"',
+            Self::Mysql => '`',
+        }
+    }
+
+    fn string(self, s: &str) -> String {
+        let escaped = s.replace('\'', "''");
+        match self {
+            Self::Ansi => format!("'{}'", escaped),
+            Self::Mysql => format!("'{}'", escaped.replace('\\', "\\\\")),
+        }
+    }
+}
+
+/// Split a database url like `postgres://user@host/db/schema.table?opt=v` into the
+/// connection url `postgres://user@host/db?opt=v` and the table `schema.table`
+pub(crate) fn split_table(url: &str) -> Result<(String, String)> {
+    let (path, query) = match url.split_once('?') {
+        Some((path, query)) => (path, Some(query)),
+        None => (url, None),
+    };
+    let (conn, table) = path
+        .rsplit_once('/')
+        .filter(|(conn, table)| !table.is_empty() && conn.matches('/').count() > 2)
+        .ok_or(anyhow!("database and table must be given in {}", url))?;
+
+    let conn = match query {
+        Some(query) => format!("{}?{}", conn, query),
+        None => conn.to_string(),
+    };
+    Ok((conn, table.to_string()))
+}
+
+/// Build the query run by the database: the needed columns, the where clause if
+/// it can be translated and the limit if the where clause was pushed down as a whole
+pub(crate) fn select_sql(table: &str, pushdown: &Pushdown, dialect: Dialect) -> String {
+    let columns = match &pushdown.columns {
+        Some(columns) if !columns.is_empty() => columns
+            .iter()
+            .map(|c| quote_ident(c, dialect.quote()))
+            .collect::<Vec<_>>()
+            .join(", "),
+        _ => "*".to_string(),
+    };
+    let mut sql = format!("SELECT {} FROM {}", columns, table);
+
+    let condition = match pushdown.condition {
+        Some(expr) => render_condition(expr, dialect),
+        None => Some(String::new()),
+    };
+    if let Some(condition) = condition {
+        if !condition.is_empty() {
+            sql.push_str(" WHERE ");
+            sql.push_str(&condition);
+        }
+        if let Some(limit) = pushdown.limit {
+            sql.push_str(&format!(" LIMIT {}", limit));
+        }
+    }
+    sql
+}
+
+/// The database evaluates the whole where clause, which must not be applied again:
+/// it compares with the types of the database, e.g. dates with text
+pub(crate) fn evaluates_condition(pushdown: &Pushdown) -> bool {
+    pushdown
+        .condition
+        .is_some_and(|expr| render_condition(expr, Dialect::Ansi).is_some())
+}
+
+/// Quote each part of a possibly qualified name, e.g. `schema.table`
+pub(crate) fn quote_table(name: &str, quote: char) -> String {
+    name.split('.')
+        .map(|part| quote_ident(part, quote))
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+pub(crate) fn quote_ident(name: &str, quote: char) -> String {
+    let escaped = name.replace(quote, &format!("{}{}", quote, quote));
+    format!("{}{}{}", quote, escaped, quote)
+}
+
+/// Translate simple comparisons combined with AND/OR, `None` if anything else is used
+fn render_condition(expr: &SqlExpr, dialect: Dialect) -> Option<String> {
+    match expr {
+        SqlExpr::Identifier(id) if id.value.contains('.') => None,
+        SqlExpr::Identifier(id) => Some(quote_ident(&id.value, dialect.quote())),
+        SqlExpr::Value(v) => match v {
+            SqlValue::Number(n, _) => Some(n.clone()),
+            SqlValue::SingleQuotedString(s) => Some(dialect.string(s)),
+            SqlValue::Boolean(b) => Some(b.to_string().to_uppercase()),
+            SqlValue::Null => Some("NULL".to_string()),
+            _ => None,
+        },
+        SqlExpr::BinaryOp { left, op, right } => {
+            let op = match op {
+                BinaryOperator::Eq => "=",
+                BinaryOperator::NotEq => "<>",
+                BinaryOperator::Lt => "<",
+                BinaryOperator::LtEq => "<=",
+                BinaryOperator::Gt => ">",
+                BinaryOperator::GtEq => ">=",
+                BinaryOperator::And => "AND",
+                BinaryOperator::Or => "OR",
+                _ => return None,
+            };
+            Some(format!(
+                "({} {} {})",
+                render_condition(left, dialect)?,
+                op,
+                render_condition(right, dialect)?
+            ))
+        }
+        SqlExpr::Nested(e) => render_condition(e, dialect),
+        SqlExpr::IsNull(e) => Some(format!("{} IS NULL", render_condition(e, dialect)?)),
+        SqlExpr::IsNotNull(e) => Some(format!("{} IS NOT NULL", render_condition(e, dialect)?)),
+        SqlExpr::UnaryOp {
+            op: UnaryOperator::Not,
+            expr,
+        } => Some(format!("NOT {}", render_condition(expr, dialect)?)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ast_convert::Sql, dialect::SqlDialect};
+    use sqlparser::parser::Parser;
+
+    fn select(sql: &str, dialect: Dialect) -> String {
+        let statement = &Parser::parse_sql(&SqlDialect, sql).unwrap()[0];
+        let sql: Sql = statement.try_into().unwrap();
+        select_sql("t", &sql.pushdown, dialect)
+    }
+
+    #[test]
+    fn select_sql_should_push_down_condition_and_limit() {
+        assert_eq!(
+            r#"SELECT "a", "b" FROM t WHERE (("b" > 1) AND ("a" = 'x''y')) LIMIT 7"#,
+            select(
+                "select a from sqlite://db where b > 1 and a = 'x''y' limit 5 offset 2",
+                Dialect::Ansi
+            )
+        );
+        assert_eq!(
+            "SELECT * FROM t LIMIT 3",
+            select("select * from sqlite://db limit 3", Dialect::Mysql)
+        );
+        assert_eq!(
+            "SELECT `a` FROM t",
+            select(
+                "select a from sqlite://db order by a limit 3",
+                Dialect::Mysql
+            )
+        );
+    }
+
+    #[test]
+    fn split_table_should_work() {
+        assert_eq!(
+            (
+                "postgres://u:p@localhost:5432/db?sslmode=disable".to_string(),
+                "public.orders".to_string()
+            ),
+            split_table("postgres://u:p@localhost:5432/db/public.orders?sslmode=disable").unwrap()
+        );
+        assert!(split_table("mysql://localhost/orders").is_err());
+        assert!(split_table("mysql://localhost/db/").is_err());
+    }
+
+    #[test]
+    fn select_sql_should_not_push_limit_without_condition() {
+        // `+` is not translated, so neither the condition nor the limit are pushed down
+        assert_eq!(
+            r#"SELECT "a" FROM t"#,
+            select(
+                "select a from sqlite://db where a + 1 > 2 limit 3",
+                Dialect::Ansi
+            )
+        );
+    }
+
+    #[test]
+    fn select_sql_should_escape_backslashes_for_mysql() {
+        let sql = r"select a from mysql://db where a = 'x\y' and b = 'it''s'";
+        assert_eq!(
+            r"SELECT `a`, `b` FROM t WHERE ((`a` = 'x\\y') AND (`b` = 'it''s'))",
+            select(sql, Dialect::Mysql)
+        );
+        assert_eq!(
+            r#"SELECT "a", "b" FROM t WHERE (("a" = 'x\y') AND ("b" = 'it''s'))"#,
+            select(sql, Dialect::Ansi)
+        );
+    }
+}
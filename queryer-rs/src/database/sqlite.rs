@@ -0,0 +1,187 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use rusqlite::{types::ValueRef, Connection, OpenFlags};
+use tracing::info;
+
+use super::{quote_table, select_sql, Dialect};
+use crate::{
+    ast_convert::Pushdown,
+    column::{to_data_frame, Cell, ColumnBuilder, Kind},
+    DataSet,
+};
+
+/// A table or view of a SQLite database file, given as
+/// `sqlite://path/to/db.sqlite?table=orders` or `sqlite://path/to/db.sqlite/orders`
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct SqliteSource {
+    path: PathBuf,
+    table: String,
+}
+
+impl SqliteSource {
+    pub(crate) fn parse(name: &str) -> Result<Self> {
+        let (path, table) = match name.split_once('?') {
+            Some((path, query)) => {
+                let table = query
+                    .split('&')
+                    .find_map(|kv| kv.strip_prefix("table="))
+                    .ok_or(anyhow!("table is not specified in {}", name))?;
+                (path, table)
+            }
+            None => name
+                .rsplit_once('/')
+                .ok_or(anyhow!("table is not specified in {}", name))?,
+        };
+        if path.is_empty() || table.is_empty() {
+            return Err(anyhow!("invalid sqlite source {}", name));
+        }
+
+        Ok(Self {
+            path: PathBuf::from(path),
+            table: table.to_string(),
+        })
+    }
+
+    pub(crate) async fn load(self, pushdown: &Pushdown<'_>) -> Result<DataSet> {
+        let sql = select_sql(&quote_table(&self.table, '"'), pushdown, Dialect::Ansi);
+        info!("loading {} with: {}", self.path.display(), sql);
+
+        tokio::task::spawn_blocking(move || read(&self.path, &sql)).await?
+    }
+}
+
+fn read(path: &Path, sql: &str) -> Result<DataSet> {
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .with_context(|| format!("failed to open {}", path.display()))?;
+    let mut stmt = conn.prepare(sql)?;
+
+    let mut columns: Vec<_> = stmt
+        .columns()
+        .iter()
+        .map(|c| ColumnBuilder::new(c.name(), kind(c.decl_type())))
+        .collect();
+
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        for (i, column) in columns.iter_mut().enumerate() {
+            column.push(cell(row.get_ref(i)?));
+        }
+    }
+
+    Ok(DataSet(to_data_frame(columns)?))
+}
+
+/// Follow SQLite's type affinity rules, https://www.sqlite.org/datatype3.html
+fn kind(decl_type: Option<&str>) -> Kind {
+    let decl = decl_type.unwrap_or_default().to_uppercase();
+    match decl.as_str() {
+        "" => Kind::Unknown,
+        "DATE" => Kind::Date,
+        "DATETIME" | "TIMESTAMP" => Kind::Datetime,
+        d if d.contains("BOOL") => Kind::Bool,
+        d if d.contains("INT") => Kind::Int,
+        d if ["CHAR", "CLOB", "TEXT", "BLOB"]
+            .iter()
+            .any(|t| d.contains(t)) =>
+        {
+            Kind::Text
+        }
+        _ => Kind::Float,
+    }
+}
+
+fn cell(value: ValueRef) -> Cell {
+    match value {
+        ValueRef::Null => Cell::Null,
+        ValueRef::Integer(i) => Cell::Int(i),
+        ValueRef::Real(f) => Cell::Float(f),
+        ValueRef::Text(t) | ValueRef::Blob(t) => Cell::Text(String::from_utf8_lossy(t)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query;
+    use polars::prelude::*;
+
+    fn create_db(dir: &tempfile::TempDir) -> PathBuf {
+        let path = dir.path().join("shop.sqlite");
+        let conn = Connection::open(&path).unwrap();
+        conn.execute_batch(
+            r#"
+            CREATE TABLE orders (
+                id INTEGER PRIMARY KEY,
+                customer TEXT,
+                amount REAL,
+                paid BOOLEAN,
+                created DATE
+            );
+            INSERT INTO orders VALUES
+                (1, 'ann', 10.5, 1, '2023-01-02'),
+                (2, 'bob', 99.0, 0, '2023-01-03'),
+                (3, 'ann', 20.0, 1, '2023-02-01'),
+                (4, 'cid', NULL, NULL, NULL);
+            CREATE VIEW big_orders AS SELECT id, amount * 2 AS double FROM orders WHERE amount > 15;
+            "#,
+        )
+        .unwrap();
+        path
+    }
+
+    #[test]
+    fn parse_sqlite_source_should_work() {
+        assert_eq!(
+            SqliteSource {
+                path: "./db.sqlite".into(),
+                table: "orders".into()
+            },
+            SqliteSource::parse("./db.sqlite?table=orders").unwrap()
+        );
+        assert_eq!(
+            SqliteSource {
+                path: "/tmp/db.sqlite".into(),
+                table: "orders".into()
+            },
+            SqliteSource::parse("/tmp/db.sqlite/orders").unwrap()
+        );
+        assert!(SqliteSource::parse("db.sqlite?x=1").is_err());
+    }
+
+    #[tokio::test]
+    async fn query_sqlite_should_work() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = create_db(&dir);
+
+        let sql = format!(
+            "select id, customer, amount from sqlite://{}?table=orders where customer = 'ann' limit 1",
+            path.display()
+        );
+        let ds = query(sql).await.unwrap();
+        assert_eq!((1, 3), ds.shape());
+        assert_eq!(&DataType::Int64, ds.column("id").unwrap().dtype());
+        assert_eq!(&DataType::Float64, ds.column("amount").unwrap().dtype());
+
+        let sql = format!("select * from sqlite://{}/orders", path.display());
+        let ds = query(sql).await.unwrap();
+        assert_eq!((4, 5), ds.shape());
+        assert_eq!(&DataType::Boolean, ds.column("paid").unwrap().dtype());
+        assert_eq!(&DataType::Date, ds.column("created").unwrap().dtype());
+
+        let sql = format!(
+            "select id, double from sqlite://{}/big_orders order by id desc",
+            path.display()
+        );
+        let ds = query(sql).await.unwrap();
+        assert_eq!((2, 2), ds.shape());
+        assert_eq!(&DataType::Float64, ds.column("double").unwrap().dtype());
+
+        // sqlite converts the text to a number, polars would compare it to the floats
+        let sql = format!(
+            "select id from sqlite://{}/orders where amount = '99'",
+            path.display()
+        );
+        assert_eq!((1, 1), query(sql).await.unwrap().shape());
+    }
+}
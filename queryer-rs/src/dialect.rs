@@ -1,3 +1,4 @@
+use anyhow::{anyhow, Result};
 use sqlparser::dialect::Dialect;
 
 #[derive(Debug, Default)]
@@ -12,10 +13,104 @@ impl Dialect for SqlDialect {
         ch.is_ascii_lowercase()
             || ch.is_ascii_uppercase()
             || ch.is_ascii_digit()
-            || [':', '/', '?', '&', '=', '-', '_', '.'].contains(&ch)
+            || [
+                ':', '/', '?', '&', '=', '-', '_', '.', '#', '@', '+', '[', ']',
+            ]
+            .contains(&ch)
     }
 }
 
+/// Clauses whose expressions may use accessors; the others, most importantly the
+/// `FROM` source url, are left as written
+const ACCESSOR_CLAUSES: [&str; 3] = ["select", "where", "order"];
+const OTHER_CLAUSES: [&str; 6] = ["from", "join", "group", "having", "limit", "offset"];
+
+/// Rewrite the accessors `a[0]`, `a['b']`, `a->'b'` and `a->>'b'` into the dotted
+/// paths `a.0` and `a.b`, which are resolved against the columns once the data is loaded.
+/// Only the projection, `WHERE` and `ORDER BY` are rewritten
+pub(crate) fn rewrite_accessors(sql: &str) -> Result<String> {
+    let mut out = String::with_capacity(sql.len());
+    let mut chars = sql.chars().peekable();
+    let mut quote = None;
+    let mut rewrite = false;
+
+    while let Some(c) = chars.next() {
+        if quote.is_none()
+            && c.is_ascii_alphabetic()
+            && !out.ends_with(|c| SqlDialect.is_identifier_part(c))
+        {
+            let mut rest = chars.clone();
+            let mut word = c.to_ascii_lowercase().to_string();
+            while let Some(c) = rest.next_if(|c| c.is_ascii_alphanumeric()) {
+                word.push(c.to_ascii_lowercase());
+            }
+            if !rest
+                .peek()
+                .is_some_and(|&c| SqlDialect.is_identifier_part(c))
+            {
+                if ACCESSOR_CLAUSES.contains(&word.as_str()) {
+                    rewrite = true;
+                } else if OTHER_CLAUSES.contains(&word.as_str()) {
+                    rewrite = false;
+                }
+            }
+        }
+
+        match (quote, c) {
+            (Some(q), c) => {
+                out.push(c);
+                if c == q {
+                    quote = None;
+                }
+            }
+            (None, '\'' | '"') => {
+                out.push(c);
+                quote = Some(c);
+            }
+            (None, '[') | (None, '-') if rewrite && (c == '[' || chars.peek() == Some(&'>')) => {
+                if c == '-' {
+                    chars.next();
+                    chars.next_if_eq(&'>');
+                }
+                while chars.next_if(|c| c.is_whitespace()).is_some() {}
+                let key = match chars.peek() {
+                    Some(&q @ ('\'' | '"')) => {
+                        chars.next();
+                        let key: String = chars.by_ref().take_while(|c| *c != q).collect();
+                        key
+                    }
+                    _ => {
+                        let mut key = String::new();
+                        while let Some(c) = chars.next_if(|c| c.is_ascii_digit()) {
+                            key.push(c);
+                        }
+                        key
+                    }
+                };
+                if c == '[' {
+                    while chars.next_if(|c| c.is_whitespace()).is_some() {}
+                    if chars.next() != Some(']') {
+                        return Err(anyhow!("expect ] after [{}", key));
+                    }
+                }
+                if key.is_empty()
+                    || !key
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+                {
+                    return Err(anyhow!("field {:?} can not be accessed", key));
+                }
+
+                out.truncate(out.trim_end().len());
+                out.push('.');
+                out.push_str(&key);
+            }
+            (None, c) => out.push(c),
+        }
+    }
+    Ok(out)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -37,4 +132,56 @@ mod tests {
     fn it_works() {
         assert!(Parser::parse_sql(&SqlDialect::default(), &example_sql()).is_ok());
     }
+
+    #[test]
+    fn rewrite_accessors_should_work() {
+        assert_eq!(
+            "select a.b.0, payload.id from t where c.x = '[1]->2'",
+            rewrite_accessors(
+                "select a['b'][0], payload ->> 'id' from t where c -> \"x\" = '[1]->2'"
+            )
+            .unwrap()
+        );
+        assert_eq!(
+            "select a - 1 from t",
+            rewrite_accessors("select a - 1 from t").unwrap()
+        );
+        assert_eq!(
+            "select a.0 from http://x/items?a[0]=1&b->c where a.b = 1 order by a.0",
+            rewrite_accessors(
+                "select a[0] from http://x/items?a[0]=1&b->c where a['b'] = 1 order by a[0]"
+            )
+            .unwrap()
+        );
+        assert!(rewrite_accessors("select a['b c'] from t").is_err());
+        assert!(rewrite_accessors("select a[0 from t").is_err());
+    }
+
+    #[tokio::test]
+    async fn query_should_keep_accessors_in_source_url() {
+        use wiremock::{matchers::query_param, Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        let body =
+            r#"[{"payload": {"id": 1}, "tags": ["a"]}, {"payload": {"id": 2}, "tags": ["b"]}]"#;
+        Mock::given(query_param("ids[0]", "1"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(body, "application/json"))
+            .mount(&server)
+            .await;
+
+        let sql = format!(
+            "SELECT payload->>'id' FROM {}/items?ids[0]=1 WHERE tags[0] = 'b' ORDER BY payload['id']",
+            server.uri()
+        );
+        let ds = crate::query(sql).await.unwrap();
+        assert_eq!(1, ds.height());
+        let ids: Vec<_> = ds
+            .column("payload.id")
+            .unwrap()
+            .i64()
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(vec![Some(2)], ids);
+    }
 }
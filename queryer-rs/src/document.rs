@@ -0,0 +1,185 @@
+use std::fmt;
+
+use indexmap::IndexMap;
+use serde::{
+    de::{MapAccess, SeqAccess, Visitor},
+    Deserialize, Deserializer, Serialize,
+};
+
+/// A JSON document like `serde_json::Value`, except that mappings keep the order of
+/// their keys, which becomes the order of the columns read from them
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub(crate) enum Document {
+    #[default]
+    Null,
+    Bool(bool),
+    Number(serde_json::Number),
+    String(String),
+    Array(Vec<Document>),
+    Object(IndexMap<String, Document>),
+}
+
+impl Document {
+    /// Look up a value by a JSON pointer, e.g. `/data/0/items`
+    pub(crate) fn pointer_mut(&mut self, pointer: &str) -> Option<&mut Document> {
+        tokens(pointer)?
+            .into_iter()
+            .try_fold(self, |target, token| match target {
+                Self::Object(map) => map.get_mut(&token),
+                Self::Array(values) => values.get_mut(token.parse::<usize>().ok()?),
+                _ => None,
+            })
+    }
+
+    pub(crate) fn pointer(&self, pointer: &str) -> Option<&Document> {
+        tokens(pointer)?
+            .into_iter()
+            .try_fold(self, |target, token| match target {
+                Self::Object(map) => map.get(&token),
+                Self::Array(values) => values.get(token.parse::<usize>().ok()?),
+                _ => None,
+            })
+    }
+
+    /// The value of `key` if this is a mapping
+    pub(crate) fn get(&self, key: &str) -> Option<&Document> {
+        match self {
+            Self::Object(map) => map.get(key),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn take(&mut self) -> Document {
+        std::mem::take(self)
+    }
+
+    pub(crate) fn is_null(&self) -> bool {
+        matches!(self, Self::Null)
+    }
+
+    pub(crate) fn is_array(&self) -> bool {
+        matches!(self, Self::Array(_))
+    }
+
+    pub(crate) fn is_object(&self) -> bool {
+        matches!(self, Self::Object(_))
+    }
+}
+
+/// The unescaped reference tokens of a JSON pointer, `None` if it is not one
+fn tokens(pointer: &str) -> Option<Vec<String>> {
+    if pointer.is_empty() {
+        return Some(Vec::new());
+    }
+    let tokens = pointer.strip_prefix('/')?.split('/');
+    Some(
+        tokens
+            .map(|token| token.replace("~1", "/").replace("~0", "~"))
+            .collect(),
+    )
+}
+
+impl From<i64> for Document {
+    fn from(i: i64) -> Self {
+        Self::Number(i.into())
+    }
+}
+
+/// Non-finite floats are null, as in `serde_json::Value`
+impl From<f64> for Document {
+    fn from(f: f64) -> Self {
+        serde_json::Number::from_f64(f).map_or(Self::Null, Self::Number)
+    }
+}
+
+impl<'de> Deserialize<'de> for Document {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(DocumentVisitor)
+    }
+}
+
+struct DocumentVisitor;
+
+impl<'de> Visitor<'de> for DocumentVisitor {
+    type Value = Document;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a JSON value")
+    }
+
+    fn visit_bool<E>(self, b: bool) -> Result<Document, E> {
+        Ok(Document::Bool(b))
+    }
+
+    fn visit_i64<E>(self, i: i64) -> Result<Document, E> {
+        Ok(i.into())
+    }
+
+    fn visit_u64<E>(self, u: u64) -> Result<Document, E> {
+        Ok(Document::Number(u.into()))
+    }
+
+    fn visit_f64<E>(self, f: f64) -> Result<Document, E> {
+        Ok(f.into())
+    }
+
+    fn visit_str<E>(self, s: &str) -> Result<Document, E> {
+        Ok(Document::String(s.to_string()))
+    }
+
+    fn visit_string<E>(self, s: String) -> Result<Document, E> {
+        Ok(Document::String(s))
+    }
+
+    fn visit_unit<E>(self) -> Result<Document, E> {
+        Ok(Document::Null)
+    }
+
+    fn visit_none<E>(self) -> Result<Document, E> {
+        Ok(Document::Null)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Document, D::Error> {
+        Document::deserialize(deserializer)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Document, A::Error> {
+        let mut values = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(value) = seq.next_element()? {
+            values.push(value);
+        }
+        Ok(Document::Array(values))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Document, A::Error> {
+        let mut values = IndexMap::with_capacity(map.size_hint().unwrap_or(0));
+        while let Some((key, value)) = map.next_entry()? {
+            values.insert(key, value);
+        }
+        Ok(Document::Object(values))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn document_should_keep_key_order() {
+        let data = r#"{"z": 1, "a": [{"y": true, "b": null}], "m": 1.5}"#;
+        let mut document: Document = serde_json::from_str(data).unwrap();
+        assert_eq!(
+            r#"{"z":1,"a":[{"y":true,"b":null}],"m":1.5}"#,
+            serde_json::to_string(&document).unwrap()
+        );
+        assert_eq!(
+            Some(&mut Document::Bool(true)),
+            document.pointer_mut("/a/0/y")
+        );
+        assert_eq!(None, document.pointer_mut("/a/1"));
+        let records = document.pointer_mut("/a").map(Document::take).unwrap();
+        assert!(matches!(records, Document::Array(values) if values.len() == 1));
+        assert_eq!(Some(&mut Document::Null), document.pointer_mut("/a"));
+    }
+}
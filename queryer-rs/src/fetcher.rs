@@ -1,67 +1,193 @@
-use std::{
-    ffi::OsStr,
-    path::{Path, PathBuf},
-};
+use std::path::{Path, PathBuf};
 
-use crate::filetype;
+use crate::{
+    compression::{self, Compression},
+    filetype,
+};
 use anyhow::{anyhow, Result};
-use tokio::fs;
+use bytes::Bytes;
+use tokio::{fs, io::AsyncReadExt};
+
+pub use self::cache::{clear_cache, configure_cache, invalidate_cache, CacheConfig};
+pub use self::http::{
+    register_http_settings, unregister_http_settings, HttpAuth, HttpSettings, RetryPolicy, ANY_HOST,
+};
+pub(crate) use self::{
+    api::ApiFetcher,
+    http::{HttpRangeReader, HttpStream},
+};
+use self::{api::ApiOptions, data_uri::DataUriFetcher, http::HttpFetcher};
+
+mod api;
+mod cache;
+mod data_uri;
+mod http;
 
+/// Fetch the raw bytes of a source together with its detected filetype.
+#[allow(async_fn_in_trait)]
 pub trait Fetch {
     type Error;
-    async fn fetch(&self) -> Result<(filetype::Filetype, String), Self::Error>;
+    async fn fetch(&self) -> Result<(filetype::Filetype, Bytes), Self::Error>;
+}
+
+/// Fetch `source`. A `#fragment` is not part of the location, it selects the file to
+/// read from a zip archive, e.g. `file://data.zip#inner.csv`. Fragments starting with
+/// `/` are left to the reader, e.g. the JSON pointer in `file://resp.json#/data/items`.
+///
+/// Besides `file://` and `http(s)://`, data can be read from `stdin://<format>`
+/// (`stdin://` sniffs the format), from inline `data:` uris and from paginated
+/// JSON apis with `api+http(s)://`, following `Link` headers.
+pub async fn retrieve_data(source: impl AsRef<str>) -> Result<(filetype::Filetype, Bytes)> {
+    let (file_type, data, _) = fetch_source(source.as_ref()).await?;
+    Ok((file_type, data))
 }
 
-pub async fn retrieve_data(source: impl AsRef<str>) -> Result<(filetype::Filetype, String)> {
-    let source = source.as_ref();
+/// Like [`retrieve_data`], also giving the zip entry the data was unpacked from
+async fn fetch_source(source: &str) -> Result<(filetype::Filetype, Bytes, Option<String>)> {
+    let (source, fragment) = match source.split_once('#') {
+        Some((source, fragment)) => (source, Some(fragment).filter(|f| !f.starts_with('/'))),
+        None => (source, None),
+    };
+
+    if source.starts_with("data:") {
+        let (file_type, data) = DataUriFetcher(source).fetch().await?;
+        return decompress_data("", fragment, file_type, data);
+    }
 
     let (protocol, name) = source
         .split_once("://")
         .ok_or(anyhow!("protocol is not specified in source"))?;
 
-    match protocol {
-        "file" => FileFetcher(PathBuf::from(name).as_path()).fetch().await,
-        "http" | "https" => HttpFetcher(source).fetch().await,
-        _ => todo!(),
-    }
+    let (file_type, data) = match protocol {
+        "file" => FileFetcher(PathBuf::from(name).as_path()).fetch().await?,
+        "stdin" => StdinFetcher(name).fetch().await?,
+        "http" | "https" => HttpFetcher(source).fetch().await?,
+        "api+http" | "api+https" => {
+            ApiFetcher(&source["api+".len()..], ApiOptions::default())
+                .fetch()
+                .await?
+        }
+        _ => return Err(anyhow!("protocol {} is not supported", protocol)),
+    };
+
+    decompress_data(name, fragment, file_type, data)
 }
 
-struct HttpFetcher<'a>(&'a str);
-struct FileFetcher<'a>(&'a Path);
+/// Data of a source, in memory, still being downloaded or read in parts
+pub(crate) enum SourceData {
+    /// with the name of the zip entry the data was unpacked from
+    Bytes(filetype::Filetype, Bytes, Option<String>),
+    Stream(filetype::Filetype, Box<HttpStream>),
+    Ranges(filetype::Filetype, Box<HttpRangeReader>),
+}
 
-impl<'a> Fetch for HttpFetcher<'a> {
-    type Error = anyhow::Error;
+/// Like [`retrieve_data`], but csv and tsv downloads are handed out as a stream, so
+/// they can be parsed while they arrive. Compressed ones are decompressed on the fly,
+/// except zip archives which need random access. Parquet files are read with range
+/// requests if the server supports them.
+pub(crate) async fn retrieve(source: &str) -> Result<SourceData> {
+    let (url, fragment) = match source.split_once('#') {
+        Some((url, fragment)) => (url, Some(fragment).filter(|f| !f.starts_with('/'))),
+        None => (source, None),
+    };
+    if !(url.starts_with("http://") || url.starts_with("https://")) {
+        let (file_type, data, entry) = fetch_source(source).await?;
+        return Ok(SourceData::Bytes(file_type, data, entry));
+    }
 
-    async fn fetch(&self) -> Result<(filetype::Filetype, String), Self::Error> {
-        let resp = reqwest::get(self.0).await?;
-        // 1. try to get filetype from content-type header
-        let content_type = resp
-            .headers()
-            .get("Content-Type")
-            .and_then(|x| x.to_str().ok().map(|s| s.split('/').last()))
-            .flatten();
-        let file_type = filetype::get_data_filetype(content_type);
-        if file_type != filetype::Filetype::Unknown {
-            return Ok((file_type, resp.text().await?));
+    let (name_type, compression) = filetype::detect_from_name(url);
+    if name_type == filetype::Filetype::Parquet && compression.is_none() {
+        if let Some(reader) = HttpRangeReader::open(url).await? {
+            return Ok(SourceData::Ranges(name_type, Box::new(reader)));
         }
+    }
 
-        // 2. try to get filetype from url
-        let last_part = self.0.split('/').last();
+    let (file_type, mut stream) = HttpFetcher(url).stream().await?;
+    if let Some(compression) = stream.content_compression() {
+        stream = stream.decompressed(compression)?;
+    }
+    let compression = match compression {
+        Some(compression) => Some(compression),
+        None => stream
+            .peek()
+            .await?
+            .and_then(|c| Compression::from_magic(c)),
+    };
+    let file_type = match (compression, name_type) {
+        (Some(_), filetype::Filetype::Unknown) | (None, _) => file_type,
+        (Some(_), name_type) => name_type,
+    };
+    let streamable = matches!(file_type, filetype::Filetype::Csv | filetype::Filetype::Tsv);
+    if streamable && stream.is_plain() && compression != Some(Compression::Zip) {
+        if let Some(compression) = compression {
+            stream = stream.decompressed(compression)?;
+        }
+        return Ok(SourceData::Stream(file_type, Box::new(stream)));
+    }
 
-        let file_type = filetype::get_data_filetype(last_part.and_then(|x| x.split('.').last()));
+    let data = stream.collect().await?.decode()?;
+    let (file_type, data, entry) = decompress_data(url, fragment, file_type, data)?;
+    Ok(SourceData::Bytes(file_type, data, entry))
+}
 
-        Ok((file_type, resp.text().await?))
+/// Decompress data detected by extension or magic bytes, the file type of compressed
+/// data comes from the remaining extension (`data.csv.gz`) or the zip entry name,
+/// which is returned as well.
+fn decompress_data(
+    name: &str,
+    fragment: Option<&str>,
+    file_type: filetype::Filetype,
+    data: Bytes,
+) -> Result<(filetype::Filetype, Bytes, Option<String>)> {
+    let (name_type, compression) = filetype::detect_from_name(name);
+    let compression = compression.or_else(|| {
+        // a zip archive is only unpacked when nothing else claims the data
+        Compression::from_magic(&data)
+            .filter(|c| *c != Compression::Zip || file_type == filetype::Filetype::Unknown)
+    });
+    let Some(compression) = compression else {
+        return Ok((file_type, data, None));
+    };
+
+    let (data, entry) = compression::decompress(data, compression, fragment)?;
+    let inner_type = match &entry {
+        Some(entry) => filetype::detect_from_name(entry).0,
+        None => name_type,
+    };
+    match inner_type {
+        filetype::Filetype::Unknown => Ok((file_type, data, entry)),
+        _ => Ok((inner_type, data, entry)),
     }
 }
 
+struct FileFetcher<'a>(&'a Path);
+
 impl<'a> Fetch for FileFetcher<'a> {
     type Error = anyhow::Error;
 
-    async fn fetch(&self) -> Result<(filetype::Filetype, String), Self::Error> {
-        let ext = self.0.extension().and_then(OsStr::to_str);
-        let file_type = filetype::get_data_filetype(ext);
+    async fn fetch(&self) -> Result<(filetype::Filetype, Bytes), Self::Error> {
+        let (file_type, _) = filetype::detect_from_name(&self.0.to_string_lossy());
+
+        Ok((file_type, fs::read(self.0).await?.into()))
+    }
+}
+
+/// Read the data piped into the process, the format comes from `stdin://<format>`
+struct StdinFetcher<'a>(&'a str);
+
+impl<'a> Fetch for StdinFetcher<'a> {
+    type Error = anyhow::Error;
+
+    async fn fetch(&self) -> Result<(filetype::Filetype, Bytes), Self::Error> {
+        let format = self.0.trim_matches('/');
+        let file_type = filetype::get_data_filetype(Some(format));
+        if !format.is_empty() && file_type == filetype::Filetype::Unknown {
+            return Err(anyhow!("unknown format {} for stdin", format));
+        }
 
-        Ok((file_type, fs::read_to_string(self.0).await?))
+        let mut buf = Vec::new();
+        tokio::io::stdin().read_to_end(&mut buf).await?;
+        Ok((file_type, buf.into()))
     }
 }
 
@@ -76,12 +202,118 @@ mod tests {
         let url = "file://./examples/data.json";
         let data = retrieve_data(url).await.unwrap();
         assert_eq!(filetype::Filetype::Json, data.0);
-        println!("type {:?}, data {}", data.0, data.1);
+        println!("type {:?}, data {:?}", data.0, data.1);
 
         let url = "https://raw.githubusercontent.com/owid/covid-19-data/master/public/data/latest/owid-covid-latest.csv";
         let data = retrieve_data(url).await.unwrap();
         assert_eq!(filetype::Filetype::Csv, data.0);
-        println!("type {:?}, data {}", data.0, data.1);
+        println!("type {:?}, data {:?}", data.0, data.1);
+    }
+
+    #[tokio::test]
+    async fn retrieve_data_should_decompress() {
+        use std::io::Write;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data.csv.gz");
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), Default::default());
+        encoder.write_all(b"a,b\n1,2\n").unwrap();
+        std::fs::write(&path, encoder.finish().unwrap()).unwrap();
+
+        let data = retrieve_data(format!("file://{}", path.display()))
+            .await
+            .unwrap();
+        assert_eq!(filetype::Filetype::Csv, data.0);
+        assert_eq!(b"a,b\n1,2\n", data.1.as_ref());
+
+        let path = dir.path().join("data.zip");
+        let mut writer = zip::ZipWriter::new(std::fs::File::create(&path).unwrap());
+        writer.start_file("inner.json", Default::default()).unwrap();
+        writer.write_all(b"[]").unwrap();
+        writer.start_file("inner.csv", Default::default()).unwrap();
+        writer.write_all(b"a\n1\n").unwrap();
+        writer.finish().unwrap();
+
+        let data = retrieve_data(format!("file://{}#inner.csv", path.display()))
+            .await
+            .unwrap();
+        assert_eq!(filetype::Filetype::Csv, data.0);
+        assert_eq!(b"a\n1\n", data.1.as_ref());
+    }
+
+    #[tokio::test]
+    async fn retrieve_data_should_read_data_uri() {
+        let data = retrieve_data("data:text/csv,a%2Cb%0A1%2C2").await.unwrap();
+        assert_eq!(filetype::Filetype::Csv, data.0);
+        assert_eq!(b"a,b\n1,2", data.1.as_ref());
+
+        assert!(StdinFetcher("docx").fetch().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn retrieve_should_stream_plain_csv() {
+        use wiremock::{matchers::path, Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(path("/plain.csv"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw("a,b\n1,2\n", "text/csv"))
+            .mount(&server)
+            .await;
+        Mock::given(path("/latin1.csv"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_raw("a\n1\n", "text/csv; charset=latin1"),
+            )
+            .mount(&server)
+            .await;
+
+        let url = format!("{}/plain.csv", server.uri());
+        assert!(matches!(
+            retrieve(&url).await.unwrap(),
+            SourceData::Stream(filetype::Filetype::Csv, _)
+        ));
+
+        let url = format!("{}/latin1.csv", server.uri());
+        assert!(matches!(
+            retrieve(&url).await.unwrap(),
+            SourceData::Bytes(filetype::Filetype::Csv, _, None)
+        ));
+    }
+
+    #[tokio::test]
+    async fn retrieve_should_stream_compressed_csv() {
+        use std::io::Write;
+        use wiremock::{matchers::path, Mock, MockServer, ResponseTemplate};
+
+        // two gzip members, as written by concatenating files
+        let mut gzip = Vec::new();
+        for part in [&b"a,b\n1,2\n"[..], b"3,4\n"] {
+            let mut encoder = flate2::write::GzEncoder::new(Vec::new(), Default::default());
+            encoder.write_all(part).unwrap();
+            gzip.extend(encoder.finish().unwrap());
+        }
+        let server = MockServer::start().await;
+        Mock::given(path("/data.csv.gz"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(gzip.clone(), "application/gzip"))
+            .mount(&server)
+            .await;
+        Mock::given(path("/encoded.csv"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("Content-Encoding", "gzip")
+                    .set_body_raw(gzip, "text/csv"),
+            )
+            .mount(&server)
+            .await;
+
+        for name in ["data.csv.gz", "encoded.csv"] {
+            let url = format!("{}/{}", server.uri(), name);
+            let SourceData::Stream(filetype::Filetype::Csv, stream) = retrieve(&url).await.unwrap()
+            else {
+                panic!("{} should be streamed", name);
+            };
+            let body = stream.collect().await.unwrap();
+            assert_eq!(b"a,b\n1,2\n3,4\n", body.data.as_ref());
+        }
     }
 
     #[test]
@@ -0,0 +1,432 @@
+use anyhow::{anyhow, Result};
+use bytes::Bytes;
+use reqwest::{header::LINK, Url};
+use tracing::{debug, warn};
+
+use super::{
+    http::{self, HttpStream},
+    Fetch,
+};
+use crate::{ast_convert::SourceArgs, document::Document, filetype};
+
+/// Keys of a page object that usually hold its records
+const RECORD_KEYS: [&str; 4] = ["data", "items", "results", "records"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Pagination {
+    /// follow the `rel="next"` url of the `Link` header
+    LinkHeader,
+    /// the field of the page at the given path holds the next url or cursor token
+    Cursor(String),
+    /// increment the `page` query parameter until a page has no records
+    Page,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ApiOptions {
+    pub(crate) pagination: Pagination,
+    /// stop after this many pages even if there are more
+    pub(crate) max_pages: usize,
+    /// path of the records in a page, found among the usual keys if not given
+    pub(crate) records: Option<String>,
+    pub(crate) page_param: String,
+    /// query parameter a cursor token is sent with
+    pub(crate) cursor_param: String,
+}
+
+impl Default for ApiOptions {
+    fn default() -> Self {
+        Self {
+            pagination: Pagination::LinkHeader,
+            max_pages: 100,
+            records: None,
+            page_param: "page".to_string(),
+            cursor_param: "cursor".to_string(),
+        }
+    }
+}
+
+impl Pagination {
+    fn parse(value: &str) -> Result<Self> {
+        match value.split_once(':') {
+            Some(("cursor", path)) if !path.is_empty() => Ok(Self::Cursor(path.to_string())),
+            None if value == "cursor" => Ok(Self::Cursor("next".to_string())),
+            None if value == "link-header" => Ok(Self::LinkHeader),
+            None if value == "page" => Ok(Self::Page),
+            _ => Err(anyhow!(
+                "pagination {} is not supported, use 'link-header', 'cursor:<field>' or 'page'",
+                value
+            )),
+        }
+    }
+}
+
+/// Follow the pages of a JSON api and concatenate their records, either from
+/// `api+https://...` or the `read_api(url, pagination => ...)` table function
+pub(crate) struct ApiFetcher<'a>(pub(crate) &'a str, pub(crate) ApiOptions);
+
+impl<'a> ApiFetcher<'a> {
+    /// `read_api(url, pagination => 'page', max_pages => 10, records => 'data.items')`,
+    /// or the same options given to an `api+https://...` source. The other named
+    /// arguments are left for the reader, e.g. `schema` or `null_values`.
+    pub(crate) fn from_args(source: &'a str, args: &'a SourceArgs) -> Result<(Self, SourceArgs)> {
+        let url = match (source.strip_prefix("api+"), args.positional.as_slice()) {
+            (Some(url), []) => url,
+            (None, [url]) => url,
+            (Some(_), _) => return Err(anyhow!("{} takes no positional arguments", source)),
+            (None, _) => {
+                return Err(anyhow!(
+                    "read_api takes the url as its only positional argument"
+                ))
+            }
+        };
+
+        let mut options = ApiOptions::default();
+        let mut rest = SourceArgs::default();
+        for (name, value) in &args.named {
+            match name.to_lowercase().as_str() {
+                "pagination" => options.pagination = Pagination::parse(value)?,
+                "max_pages" => options.max_pages = value.parse()?,
+                "records" => options.records = Some(value.clone()),
+                "page_param" => options.page_param = value.clone(),
+                "cursor_param" => options.cursor_param = value.clone(),
+                _ => rest.named.push((name.clone(), value.clone())),
+            }
+        }
+        Ok((Self(url, options), rest))
+    }
+}
+
+impl<'a> Fetch for ApiFetcher<'a> {
+    type Error = anyhow::Error;
+
+    async fn fetch(&self) -> Result<(filetype::Filetype, Bytes), Self::Error> {
+        let options = &self.1;
+        let start = Url::parse(self.0)?;
+        let first_page: u64 = param(&start, &options.page_param)
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(1);
+
+        let mut url = start.clone();
+        let mut records = Vec::new();
+        for n in 1..=options.max_pages {
+            debug!("fetching page {} from {}", n, url);
+            let resp = http::get(url.as_str()).await?;
+            let link = next_link(resp.headers());
+            let body = HttpStream::from_response(url.as_str(), resp)?
+                .collect()
+                .await?
+                .decode()?;
+            let page: Document = serde_json::from_slice(&body)?;
+
+            // taken before the records are moved out of the page
+            let cursor = match &options.pagination {
+                Pagination::Cursor(path) => lookup(&page, path).cloned(),
+                _ => None,
+            };
+            let items = page_records(page, options.records.as_deref())?;
+            let empty = items.is_empty();
+            records.extend(items);
+
+            let next = match &options.pagination {
+                Pagination::LinkHeader => link.map(|l| url.join(&l)).transpose()?,
+                Pagination::Cursor(_) => match &cursor {
+                    Some(Document::String(s)) if is_link(s) => Some(url.join(s)?),
+                    Some(Document::String(s)) if !s.is_empty() => {
+                        Some(with_param(&start, &options.cursor_param, s))
+                    }
+                    Some(Document::Number(n)) => {
+                        Some(with_param(&start, &options.cursor_param, &n.to_string()))
+                    }
+                    _ => None,
+                },
+                Pagination::Page if empty => None,
+                Pagination::Page => Some(with_param(
+                    &start,
+                    &options.page_param,
+                    &(first_page + n as u64).to_string(),
+                )),
+            };
+
+            match next {
+                Some(next) if n == options.max_pages => {
+                    warn!("stopped after {} pages, {} is not fetched", n, next)
+                }
+                Some(next) => url = next,
+                None => break,
+            }
+        }
+
+        let data = serde_json::to_vec(&Document::Array(records))?;
+        Ok((filetype::Filetype::Json, data.into()))
+    }
+}
+
+/// The url of `rel="next"` in a `Link` header, e.g. `<https://api/items?page=2>; rel="next"`
+fn next_link(headers: &reqwest::header::HeaderMap) -> Option<String> {
+    headers
+        .get_all(LINK)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .find_map(|link| {
+            let mut parts = link.split(';');
+            let target = parts.next()?.trim();
+            let is_next = parts.any(|p| {
+                let p = p.trim();
+                p.strip_prefix("rel=").is_some_and(|rel| {
+                    rel.trim_matches('"')
+                        .split_whitespace()
+                        .any(|r| r == "next")
+                })
+            });
+            is_next.then(|| {
+                target
+                    .trim_start_matches('<')
+                    .trim_end_matches('>')
+                    .to_string()
+            })
+        })
+}
+
+/// Records of a page: the page itself if it is an array, the array at `path`, or the
+/// first array found under the usual keys
+fn page_records(page: Document, path: Option<&str>) -> Result<Vec<Document>> {
+    let records = match path {
+        Some(path) => lookup(&page, path)
+            .cloned()
+            .ok_or(anyhow!("records {} not found in page", path))?,
+        None => match page {
+            Document::Object(mut map) => {
+                match RECORD_KEYS
+                    .iter()
+                    .find(|k| map.get(**k).is_some_and(Document::is_array))
+                {
+                    Some(key) => map.shift_remove(*key).unwrap_or_default(),
+                    None => Document::Object(map),
+                }
+            }
+            page => page,
+        },
+    };
+
+    match records {
+        Document::Array(items) => Ok(items),
+        Document::Null => Ok(Vec::new()),
+        record => Ok(vec![record]),
+    }
+}
+
+/// Look up a field by a dotted path `meta.next` or a JSON pointer `/meta/next`
+fn lookup<'v>(value: &'v Document, path: &str) -> Option<&'v Document> {
+    if path.starts_with('/') {
+        return value.pointer(path);
+    }
+    path.split('.')
+        .try_fold(value, |v, key| v.get(key))
+        .filter(|v| !v.is_null())
+}
+
+/// A cursor value that is a url rather than a token
+fn is_link(value: &str) -> bool {
+    value.starts_with("http://")
+        || value.starts_with("https://")
+        || value.starts_with('/')
+        || value.starts_with('?')
+}
+
+fn param(url: &Url, name: &str) -> Option<String> {
+    url.query_pairs()
+        .find(|(k, _)| k == name)
+        .map(|(_, v)| v.into_owned())
+}
+
+fn with_param(url: &Url, name: &str, value: &str) -> Url {
+    let pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(k, _)| k != name)
+        .map(|(k, v)| (k.into_owned(), v.into_owned()))
+        .collect();
+    let mut url = url.clone();
+    url.query_pairs_mut()
+        .clear()
+        .extend_pairs(pairs)
+        .append_pair(name, value);
+    url
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use polars::prelude::DataType;
+    use serde_json::Value;
+    use wiremock::{
+        matchers::{method, query_param},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    fn json(value: Value) -> ResponseTemplate {
+        ResponseTemplate::new(200).set_body_raw(value.to_string(), "application/json")
+    }
+
+    async fn fetch(url: &str, options: ApiOptions) -> Vec<Value> {
+        let (tp, data) = ApiFetcher(url, options).fetch().await.unwrap();
+        assert_eq!(filetype::Filetype::Json, tp);
+        serde_json::from_slice(&data).unwrap()
+    }
+
+    #[tokio::test]
+    async fn api_fetcher_should_follow_link_header() {
+        let server = MockServer::start().await;
+        Mock::given(query_param("page", "2"))
+            .respond_with(json(serde_json::json!([{"id": 3}])))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(
+                json(serde_json::json!([{"id": 1}, {"id": 2}])).insert_header(
+                    "Link",
+                    r#"</items?page=2>; rel="next", </items?page=2>; rel="last""#,
+                ),
+            )
+            .mount(&server)
+            .await;
+
+        let url = format!("{}/items", server.uri());
+        let records = fetch(&url, ApiOptions::default()).await;
+        assert_eq!(3, records.len());
+    }
+
+    #[tokio::test]
+    async fn api_fetcher_should_follow_cursor() {
+        let server = MockServer::start().await;
+        Mock::given(query_param("cursor", "abc"))
+            .respond_with(json(
+                serde_json::json!({"data": [{"id": 2}], "meta": {"next": null}}),
+            ))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(json(
+                serde_json::json!({"data": [{"id": 1}], "meta": {"next": "abc"}}),
+            ))
+            .mount(&server)
+            .await;
+
+        let options = ApiOptions {
+            pagination: Pagination::parse("cursor:meta.next").unwrap(),
+            ..Default::default()
+        };
+        let records = fetch(&format!("{}/items?limit=1", server.uri()), options).await;
+        assert_eq!(
+            vec![serde_json::json!({"id": 1}), serde_json::json!({"id": 2})],
+            records
+        );
+    }
+
+    #[tokio::test]
+    async fn api_fetcher_should_count_pages_up_to_limit() {
+        let server = MockServer::start().await;
+        Mock::given(query_param("p", "3"))
+            .respond_with(json(serde_json::json!({"results": []})))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(json(serde_json::json!({"results": [{"id": 1}]})))
+            .mount(&server)
+            .await;
+
+        let options = ApiOptions {
+            pagination: Pagination::Page,
+            page_param: "p".into(),
+            ..Default::default()
+        };
+        assert_eq!(2, fetch(&server.uri(), options.clone()).await.len());
+
+        let options = ApiOptions {
+            max_pages: 1,
+            ..options
+        };
+        assert_eq!(1, fetch(&server.uri(), options).await.len());
+    }
+
+    #[tokio::test]
+    async fn query_api_source_should_take_options() {
+        let server = MockServer::start().await;
+        Mock::given(query_param("cursor", "abc"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                r#"{"data": [{"name": "b", "id": 2}], "next": null}"#,
+                "application/json",
+            ))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                r#"{"data": [{"name": "a", "id": 1}], "next": "abc"}"#,
+                "application/json",
+            ))
+            .mount(&server)
+            .await;
+
+        let source = format!("api+{}/items", server.uri());
+        let sql = format!(
+            "SELECT * FROM {}(pagination => 'cursor:next') ORDER BY id",
+            source
+        );
+        let ds = crate::query(sql).await.unwrap();
+        assert_eq!(2, ds.height());
+        // the records of the pages keep the order of their keys
+        assert_eq!(vec!["name", "id"], ds.get_column_names());
+
+        // other arguments are reader options
+        let sql = format!(
+            "SELECT id FROM {}(pagination => 'cursor:next', schema => 'id: utf8')",
+            source
+        );
+        let ds = crate::query(sql).await.unwrap();
+        assert_eq!(&DataType::Utf8, ds.column("id").unwrap().dtype());
+        let sql = format!("SELECT id FROM {}(foo => 1)", source);
+        assert!(crate::query(sql).await.is_err());
+    }
+
+    #[test]
+    fn api_options_should_parse_args() {
+        let args = SourceArgs {
+            positional: vec!["https://api.xyz/items".into()],
+            named: vec![
+                ("pagination".into(), "cursor".into()),
+                ("max_pages".into(), "3".into()),
+            ],
+        };
+        let (fetcher, rest) = ApiFetcher::from_args("read_api", &args).unwrap();
+        assert_eq!("https://api.xyz/items", fetcher.0);
+        assert!(rest.is_empty());
+        assert_eq!(Pagination::Cursor("next".into()), fetcher.1.pagination);
+        assert_eq!(3, fetcher.1.max_pages);
+        assert!(ApiFetcher::from_args("api+https://api.xyz/items", &args).is_err());
+
+        let args = SourceArgs {
+            named: args.named,
+            ..Default::default()
+        };
+        let (fetcher, _) = ApiFetcher::from_args("api+https://api.xyz/items", &args).unwrap();
+        assert_eq!("https://api.xyz/items", fetcher.0);
+        assert_eq!(3, fetcher.1.max_pages);
+
+        assert!(Pagination::parse("offset").is_err());
+        // arguments of the reader are left over
+        let args = SourceArgs {
+            positional: vec!["https://api.xyz".into()],
+            named: vec![
+                ("schema".into(), "id: int32".into()),
+                ("max_pages".into(), "2".into()),
+            ],
+        };
+        let (fetcher, rest) = ApiFetcher::from_args("read_api", &args).unwrap();
+        assert_eq!(2, fetcher.1.max_pages);
+        assert_eq!(
+            vec![("schema".to_string(), "id: int32".to_string())],
+            rest.named
+        );
+    }
+}
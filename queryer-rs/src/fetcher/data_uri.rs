@@ -0,0 +1,68 @@
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use bytes::Bytes;
+use percent_encoding::percent_decode_str;
+
+use super::Fetch;
+use crate::filetype::{self, Mime};
+
+/// Fetch inline data from a RFC 2397 `data:[<mediatype>][;base64],<data>` uri
+pub(crate) struct DataUriFetcher<'a>(pub(crate) &'a str);
+
+impl<'a> Fetch for DataUriFetcher<'a> {
+    type Error = anyhow::Error;
+
+    async fn fetch(&self) -> Result<(filetype::Filetype, Bytes), Self::Error> {
+        let (header, data) = self
+            .0
+            .strip_prefix("data:")
+            .and_then(|uri| uri.split_once(','))
+            .ok_or(anyhow!("invalid data uri"))?;
+
+        let (media_type, base64) = match header.strip_suffix(";base64") {
+            Some(media_type) => (media_type, true),
+            None => (header, false),
+        };
+
+        let data: Vec<u8> = percent_decode_str(data).collect();
+        let data = match base64 {
+            true => STANDARD.decode(data.trim_ascii())?,
+            false => data,
+        };
+
+        // an empty media type defaults to text/plain
+        let Some(mime) = Mime::parse(media_type) else {
+            return Ok((filetype::Filetype::Unknown, data.into()));
+        };
+        let data = match mime.encoding() {
+            Some(encoding) => encoding.decode(&data).0.into_owned().into_bytes(),
+            None => data,
+        };
+
+        Ok((mime.filetype(), data.into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn data_uri_fetcher_should_work() {
+        let uri = format!("data:text/csv;base64,{}", STANDARD.encode("a,b\n1,2\n"));
+        let (tp, data) = DataUriFetcher(&uri).fetch().await.unwrap();
+        assert_eq!(filetype::Filetype::Csv, tp);
+        assert_eq!("a,b\n1,2\n", data);
+
+        let uri = "data:application/json,%5B%7B%22a%22%3A%201%7D%5D";
+        let (tp, data) = DataUriFetcher(uri).fetch().await.unwrap();
+        assert_eq!(filetype::Filetype::Json, tp);
+        assert_eq!("[{\"a\": 1}]", data);
+
+        let (tp, data) = DataUriFetcher("data:,a%2Cb").fetch().await.unwrap();
+        assert_eq!(filetype::Filetype::Unknown, tp);
+        assert_eq!("a,b", data);
+
+        assert!(DataUriFetcher("data:text/csv").fetch().await.is_err());
+    }
+}
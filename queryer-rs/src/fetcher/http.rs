@@ -0,0 +1,799 @@
+use std::{
+    collections::HashMap, env, io, ops::Range, path::PathBuf, pin::Pin, sync::RwLock,
+    time::Duration,
+};
+
+use anyhow::{anyhow, Context, Result};
+use bytes::{Bytes, BytesMut};
+use lazy_static::lazy_static;
+use reqwest::{
+    header::{
+        HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_ENCODING, CONTENT_RANGE, CONTENT_TYPE,
+        COOKIE, PROXY_AUTHORIZATION, RANGE,
+    },
+    Client, Response, StatusCode, Url,
+};
+use tokio::io::AsyncRead;
+use tokio_stream::StreamExt;
+use tokio_util::io::{ReaderStream, StreamReader};
+use tracing::warn;
+
+use super::{
+    cache::{self, CacheSink},
+    Fetch,
+};
+use crate::{
+    compression::{self, Compression},
+    filetype::{self, Mime},
+};
+
+lazy_static! {
+    static ref HTTP_SETTINGS: RwLock<HashMap<String, HttpSettings>> = RwLock::new(HashMap::new());
+    /// clients built from the settings of a host key, reused to keep connections alive
+    static ref HTTP_CLIENTS: RwLock<HashMap<String, Client>> = RwLock::new(HashMap::new());
+}
+
+/// Host key that matches every host without its own settings
+pub const ANY_HOST: &str = "*";
+
+/// HTTP settings applied to every request sent to a host
+#[derive(Debug, Clone)]
+pub struct HttpSettings {
+    /// extra headers sent with every request
+    pub headers: HeaderMap,
+    pub auth: Option<HttpAuth>,
+    /// timeout of the whole request, including reading the body
+    pub timeout: Option<Duration>,
+    pub connect_timeout: Option<Duration>,
+    pub retry: RetryPolicy,
+    /// proxy url used for all schemes, e.g. `http://proxy.local:3128`
+    pub proxy: Option<String>,
+    /// PEM file with extra root certificates
+    pub ca_bundle: Option<PathBuf>,
+    /// fail downloads with a larger body, `None` for no limit
+    pub max_download_size: Option<u64>,
+}
+
+/// Credentials are read from environment variables when the request is sent,
+/// so secrets never end up in the settings themselves
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HttpAuth {
+    Bearer {
+        token_env: String,
+    },
+    Basic {
+        username: String,
+        password_env: Option<String>,
+    },
+}
+
+/// Retry on connection errors, timeouts and 5xx responses with exponential backoff
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for HttpSettings {
+    fn default() -> Self {
+        Self {
+            headers: HeaderMap::new(),
+            auth: None,
+            timeout: None,
+            connect_timeout: Some(Duration::from_secs(10)),
+            retry: RetryPolicy::default(),
+            proxy: None,
+            ca_bundle: None,
+            max_download_size: None,
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 2,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(10),
+        }
+    }
+}
+
+impl RetryPolicy {
+    fn backoff(&self, attempt: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_backoff)
+    }
+}
+
+/// Register settings for a host. The key is either `host:port`, `host` or [`ANY_HOST`],
+/// looked up in that order.
+pub fn register_http_settings(host: impl Into<String>, settings: HttpSettings) {
+    let host = host.into();
+    HTTP_CLIENTS.write().unwrap().remove(&host);
+    HTTP_SETTINGS.write().unwrap().insert(host, settings);
+}
+
+pub fn unregister_http_settings(host: &str) -> Option<HttpSettings> {
+    HTTP_CLIENTS.write().unwrap().remove(host);
+    HTTP_SETTINGS.write().unwrap().remove(host)
+}
+
+pub(crate) fn http_settings_for(url: &Url) -> HttpSettings {
+    lookup_http_settings(url).1
+}
+
+/// The key the settings of `url` are registered with, empty for the defaults
+fn lookup_http_settings(url: &Url) -> (String, HttpSettings) {
+    let settings = HTTP_SETTINGS.read().unwrap();
+    let host = url.host_str().unwrap_or_default();
+
+    url.port()
+        .map(|port| format!("{}:{}", host, port))
+        .into_iter()
+        .chain([host.to_string(), ANY_HOST.to_string()])
+        .find_map(|key| settings.get(&key).map(|s| (key, s.clone())))
+        .unwrap_or_default()
+}
+
+/// The client for the settings registered with `key`, built on first use
+fn client_for(key: &str, settings: &HttpSettings) -> Result<Client> {
+    if let Some(client) = HTTP_CLIENTS.read().unwrap().get(key) {
+        return Ok(client.clone());
+    }
+    let client = settings.client()?;
+    HTTP_CLIENTS
+        .write()
+        .unwrap()
+        .insert(key.to_string(), client.clone());
+    Ok(client)
+}
+
+impl HttpSettings {
+    /// Requests carry credentials, their responses must not be shared through the cache
+    pub(crate) fn sends_credentials(&self) -> bool {
+        self.auth.is_some()
+            || [AUTHORIZATION, COOKIE, PROXY_AUTHORIZATION]
+                .iter()
+                .any(|name| self.headers.contains_key(name))
+    }
+
+    fn client(&self) -> Result<Client> {
+        let mut builder = Client::builder().default_headers(self.headers.clone());
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+        if let Some(proxy) = &self.proxy {
+            builder = builder.proxy(reqwest::Proxy::all(proxy)?);
+        }
+        if let Some(path) = &self.ca_bundle {
+            let pem = std::fs::read(path)
+                .with_context(|| format!("failed to read ca bundle {}", path.display()))?;
+            for cert in reqwest::Certificate::from_pem_bundle(&pem)? {
+                builder = builder.add_root_certificate(cert);
+            }
+        }
+        Ok(builder.build()?)
+    }
+}
+
+fn read_env(name: &str) -> Result<String> {
+    env::var(name).with_context(|| format!("environment variable {} is not set", name))
+}
+
+/// Send a GET request to `url` using the settings registered for its host
+pub(crate) async fn get(url: &str) -> Result<Response> {
+    get_with_headers(url, HeaderMap::new()).await
+}
+
+pub(crate) async fn get_with_headers(url: &str, headers: HeaderMap) -> Result<Response> {
+    let parsed = Url::parse(url)?;
+    let (key, settings) = lookup_http_settings(&parsed);
+    let client = client_for(&key, &settings)?;
+
+    let mut attempt = 0;
+    loop {
+        let mut req = client.get(parsed.clone()).headers(headers.clone());
+        req = match &settings.auth {
+            Some(HttpAuth::Bearer { token_env }) => req.bearer_auth(read_env(token_env)?),
+            Some(HttpAuth::Basic {
+                username,
+                password_env,
+            }) => {
+                let password = password_env.as_deref().map(read_env).transpose()?;
+                req.basic_auth(username, password)
+            }
+            None => req,
+        };
+
+        let err = match req.send().await {
+            Ok(resp) if !resp.status().is_server_error() => return Ok(resp.error_for_status()?),
+            Ok(resp) => anyhow!("server responded with {}", resp.status()),
+            Err(e) if e.is_connect() || e.is_timeout() => e.into(),
+            Err(e) => return Err(e.into()),
+        };
+
+        if attempt >= settings.retry.max_retries {
+            return Err(err.context(format!("request to {} failed", url)));
+        }
+        let backoff = settings.retry.backoff(attempt);
+        warn!("request to {} failed: {}, retry in {:?}", url, err, backoff);
+        tokio::time::sleep(backoff).await;
+        attempt += 1;
+    }
+}
+
+/// Body of a response with the headers needed to interpret it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct HttpBody {
+    pub(crate) content_type: Option<String>,
+    pub(crate) content_encoding: Option<String>,
+    pub(crate) data: Bytes,
+}
+
+impl HttpBody {
+    fn mime(&self) -> Option<Mime> {
+        self.content_type.as_deref().and_then(Mime::parse)
+    }
+
+    /// undo the transport level `Content-Encoding` and transcode text to utf-8
+    /// according to the `charset` parameter of the content type
+    pub(crate) fn decode(self) -> Result<Bytes> {
+        let mime = self.mime();
+        let data = match self
+            .content_encoding
+            .as_deref()
+            .and_then(Compression::from_content_encoding)
+        {
+            Some(c) => compression::decompress(self.data, c, None)?.0,
+            None => self.data,
+        };
+
+        match mime.as_ref().and_then(Mime::encoding) {
+            Some(encoding) => {
+                let (text, _, _) = encoding.decode(&data);
+                Ok(Bytes::from(text.into_owned()))
+            }
+            None => Ok(data),
+        }
+    }
+}
+
+/// A body read chunk by chunk, so large downloads can be consumed while they arrive
+pub(crate) struct HttpStream {
+    pub(crate) content_type: Option<String>,
+    pub(crate) content_encoding: Option<String>,
+    body: StreamBody,
+    /// chunk read by [`HttpStream::peek`]
+    peeked: Option<Bytes>,
+    sink: Option<CacheSink>,
+}
+
+enum StreamBody {
+    Response {
+        resp: Response,
+        url: String,
+        limit: Option<u64>,
+        read: u64,
+    },
+    Buffered(Option<Bytes>),
+    /// decompressed while the inner stream is read
+    Decoded(ReaderStream<Pin<Box<dyn AsyncRead + Send>>>),
+}
+
+fn too_large(url: &str, limit: u64) -> anyhow::Error {
+    anyhow!(
+        "{} exceeds the maximum download size of {} bytes",
+        url,
+        limit
+    )
+}
+
+impl HttpStream {
+    /// Fails right away if the announced length is over the maximum download size
+    pub(crate) fn from_response(url: &str, resp: Response) -> Result<Self> {
+        let limit = http_settings_for(&Url::parse(url)?).max_download_size;
+        if let (Some(limit), Some(len)) = (limit, resp.content_length()) {
+            if len > limit {
+                return Err(too_large(url, limit));
+            }
+        }
+
+        let header = |name| {
+            resp.headers()
+                .get(name)
+                .and_then(|x: &HeaderValue| x.to_str().ok().map(str::to_string))
+        };
+        Ok(Self {
+            content_type: header(CONTENT_TYPE),
+            content_encoding: header(CONTENT_ENCODING),
+            peeked: None,
+            sink: None,
+            body: StreamBody::Response {
+                resp,
+                url: url.to_string(),
+                limit,
+                read: 0,
+            },
+        })
+    }
+
+    pub(crate) fn from_body(body: HttpBody) -> Self {
+        Self {
+            content_type: body.content_type,
+            content_encoding: body.content_encoding,
+            body: StreamBody::Buffered(Some(body.data)),
+            peeked: None,
+            sink: None,
+        }
+    }
+
+    /// Decompress the chunks as they are read. Limits and the cache still apply to
+    /// the compressed chunks.
+    pub(crate) fn decompressed(self, compression: Compression) -> Result<Self> {
+        let content_type = self.content_type.clone();
+        let chunks = futures::stream::try_unfold(self, |mut stream| async move {
+            let chunk = stream.chunk().await.map_err(io::Error::other)?;
+            Ok::<_, io::Error>(chunk.map(|chunk| (chunk, stream)))
+        });
+        let reader = compression::async_decoder(compression, StreamReader::new(Box::pin(chunks)))?;
+        Ok(Self {
+            content_type,
+            content_encoding: None,
+            body: StreamBody::Decoded(ReaderStream::new(reader)),
+            peeked: None,
+            sink: None,
+        })
+    }
+
+    /// The compression of the `Content-Encoding` header
+    pub(crate) fn content_compression(&self) -> Option<Compression> {
+        self.content_encoding
+            .as_deref()
+            .and_then(Compression::from_content_encoding)
+    }
+
+    /// Copy the chunks into `sink` as they are read
+    pub(crate) fn with_sink(mut self, sink: CacheSink) -> Self {
+        self.sink = Some(sink);
+        self
+    }
+
+    /// The first chunk, still returned by the next call to [`HttpStream::chunk`]
+    pub(crate) async fn peek(&mut self) -> Result<Option<&Bytes>> {
+        if self.peeked.is_none() {
+            self.peeked = self.chunk().await?;
+        }
+        Ok(self.peeked.as_ref())
+    }
+
+    pub(crate) async fn chunk(&mut self) -> Result<Option<Bytes>> {
+        if let Some(chunk) = self.peeked.take() {
+            return Ok(Some(chunk));
+        }
+        let chunk = match &mut self.body {
+            StreamBody::Response {
+                resp,
+                url,
+                limit,
+                read,
+            } => match resp.chunk().await? {
+                Some(chunk) => {
+                    *read += chunk.len() as u64;
+                    match limit {
+                        Some(limit) if *read > *limit => return Err(too_large(url, *limit)),
+                        _ => Some(chunk),
+                    }
+                }
+                None => None,
+            },
+            StreamBody::Buffered(data) => data.take(),
+            StreamBody::Decoded(chunks) => match chunks.next().await {
+                Some(chunk) => Some(chunk.context("failed to decompress the download")?),
+                None => None,
+            },
+        };
+
+        if let Some(mut sink) = self.sink.take() {
+            match &chunk {
+                Some(chunk) => match sink.write(chunk).await {
+                    Ok(()) => self.sink = Some(sink),
+                    Err(e) => warn!("stop caching: {}", e),
+                },
+                None => {
+                    if let Err(e) = sink.commit().await {
+                        warn!("failed to cache: {}", e);
+                    }
+                }
+            }
+        }
+        Ok(chunk)
+    }
+
+    pub(crate) async fn collect(mut self) -> Result<HttpBody> {
+        let mut data = BytesMut::new();
+        while let Some(chunk) = self.chunk().await? {
+            data.extend_from_slice(&chunk);
+        }
+        Ok(HttpBody {
+            content_type: self.content_type,
+            content_encoding: self.content_encoding,
+            data: data.freeze(),
+        })
+    }
+
+    pub(crate) fn mime(&self) -> Option<Mime> {
+        self.content_type.as_deref().and_then(Mime::parse)
+    }
+
+    /// The chunks can be parsed as they are: no `Content-Encoding` or charset to undo
+    pub(crate) fn is_plain(&self) -> bool {
+        self.content_compression().is_none() && self.mime().and_then(|m| m.encoding()).is_none()
+    }
+}
+
+/// Reads parts of a remote file with range requests, which bypass the cache.
+/// The maximum download size applies to the bytes of all requests together.
+pub(crate) struct HttpRangeReader {
+    url: String,
+    size: u64,
+    limit: Option<u64>,
+    read: u64,
+}
+
+impl HttpRangeReader {
+    /// `None` if the server does not answer range requests
+    pub(crate) async fn open(url: &str) -> Result<Option<Self>> {
+        let resp = get_with_headers(url, range(0..1)).await?;
+        if resp.status() != StatusCode::PARTIAL_CONTENT {
+            return Ok(None);
+        }
+        // `bytes 0-0/<size>`, the size may be unknown (`*`)
+        let size = resp
+            .headers()
+            .get(CONTENT_RANGE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.rsplit_once('/'))
+            .and_then(|(_, size)| size.parse().ok());
+        let Some(size) = size else {
+            return Ok(None);
+        };
+        Ok(Some(Self {
+            url: url.to_string(),
+            size,
+            limit: http_settings_for(&Url::parse(url)?).max_download_size,
+            read: 0,
+        }))
+    }
+
+    pub(crate) fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub(crate) async fn get(&mut self, bytes: Range<u64>) -> Result<Bytes> {
+        let len = bytes.end.saturating_sub(bytes.start);
+        if len == 0 {
+            return Ok(Bytes::new());
+        }
+        self.read += len;
+        if let Some(limit) = self.limit.filter(|limit| self.read > *limit) {
+            return Err(too_large(&self.url, limit));
+        }
+
+        let resp = get_with_headers(&self.url, range(bytes.clone())).await?;
+        if resp.status() != StatusCode::PARTIAL_CONTENT {
+            return Err(anyhow!("{} did not answer the range request", self.url));
+        }
+        let data = resp.bytes().await?;
+        if data.len() as u64 != len {
+            return Err(anyhow!(
+                "{} sent {} bytes for range {:?}",
+                self.url,
+                data.len(),
+                bytes
+            ));
+        }
+        Ok(data)
+    }
+}
+
+fn range(bytes: Range<u64>) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    let value = format!("bytes={}-{}", bytes.start, bytes.end - 1);
+    headers.insert(RANGE, value.parse().unwrap());
+    headers
+}
+
+pub(crate) struct HttpFetcher<'a>(pub(crate) &'a str);
+
+impl<'a> HttpFetcher<'a> {
+    /// Start the download, going through the cache when it is enabled
+    pub(crate) async fn stream(&self) -> Result<(filetype::Filetype, HttpStream)> {
+        let stream = match cache::current() {
+            Some(cache) if cache::is_cacheable(self.0) => cache.get(self.0).await?,
+            _ => HttpStream::from_response(self.0, get(self.0).await?)?,
+        };
+
+        // 1. try to get filetype from content-type header
+        let file_type = stream
+            .mime()
+            .map_or(filetype::Filetype::Unknown, |m| m.filetype());
+        if file_type != filetype::Filetype::Unknown {
+            return Ok((file_type, stream));
+        }
+
+        // 2. try to get filetype from url
+        let (file_type, _) = filetype::detect_from_name(self.0);
+
+        Ok((file_type, stream))
+    }
+}
+
+impl<'a> Fetch for HttpFetcher<'a> {
+    type Error = anyhow::Error;
+
+    async fn fetch(&self) -> Result<(filetype::Filetype, Bytes), Self::Error> {
+        let (file_type, stream) = self.stream().await?;
+        Ok((file_type, stream.collect().await?.decode()?))
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use wiremock::{
+        matchers::{basic_auth, bearer_token, header, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    fn host_of(server: &MockServer) -> String {
+        server.uri().trim_start_matches("http://").to_string()
+    }
+
+    /// Settings registered until dropped, the port may be reused by other tests later
+    pub(crate) struct Registered(String);
+
+    impl Drop for Registered {
+        fn drop(&mut self) {
+            unregister_http_settings(&self.0);
+        }
+    }
+
+    pub(crate) fn register(host: String, settings: HttpSettings) -> Registered {
+        register_http_settings(host.clone(), settings);
+        Registered(host)
+    }
+
+    #[tokio::test]
+    async fn http_settings_should_send_headers_and_bearer_token() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/data.csv"))
+            .and(header("x-team", "data"))
+            .and(bearer_token("secret"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("a,b\n1,2\n"))
+            .mount(&server)
+            .await;
+
+        env::set_var("QUERYER_TEST_BEARER_TOKEN", "secret");
+        let mut settings = HttpSettings::default();
+        settings.headers.insert("x-team", "data".parse().unwrap());
+        settings.auth = Some(HttpAuth::Bearer {
+            token_env: "QUERYER_TEST_BEARER_TOKEN".into(),
+        });
+        let _registered = register(host_of(&server), settings);
+
+        let url = format!("{}/data.csv", server.uri());
+        let (tp, data) = HttpFetcher(&url).fetch().await.unwrap();
+        assert_eq!(filetype::Filetype::Csv, tp);
+        assert_eq!("a,b\n1,2\n", data);
+    }
+
+    #[tokio::test]
+    async fn http_settings_should_send_basic_auth() {
+        let server = MockServer::start().await;
+        Mock::given(basic_auth("user", "pass"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("[]"))
+            .mount(&server)
+            .await;
+
+        env::set_var("QUERYER_TEST_BASIC_PASSWORD", "pass");
+        let settings = HttpSettings {
+            auth: Some(HttpAuth::Basic {
+                username: "user".into(),
+                password_env: Some("QUERYER_TEST_BASIC_PASSWORD".into()),
+            }),
+            ..Default::default()
+        };
+        let _registered = register(host_of(&server), settings);
+
+        assert!(get(&format!("{}/x.json", server.uri())).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn http_fetcher_should_decode_content_encoding() {
+        use std::io::Write;
+
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), Default::default());
+        encoder.write_all(b"[{\"a\": 1}]").unwrap();
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("Content-Encoding", "gzip")
+                    .set_body_raw(encoder.finish().unwrap(), "application/json"),
+            )
+            .mount(&server)
+            .await;
+
+        let (tp, data) = HttpFetcher(&server.uri()).fetch().await.unwrap();
+        assert_eq!(filetype::Filetype::Json, tp);
+        assert_eq!("[{\"a\": 1}]", data);
+    }
+
+    #[tokio::test]
+    async fn http_fetcher_should_decode_charset() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    // "name\ncafé\n" in latin-1
+                    .set_body_raw(b"name\ncaf\xe9\n".to_vec(), "text/csv; charset=ISO-8859-1"),
+            )
+            .mount(&server)
+            .await;
+
+        let url = format!("{}/export?format=1", server.uri());
+        let (tp, data) = HttpFetcher(&url).fetch().await.unwrap();
+        assert_eq!(filetype::Filetype::Csv, tp);
+        assert_eq!("name\ncafé\n", data);
+    }
+
+    #[tokio::test]
+    async fn http_get_should_retry_on_server_error() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("ok"))
+            .mount(&server)
+            .await;
+
+        let settings = HttpSettings {
+            retry: RetryPolicy {
+                max_retries: 2,
+                initial_backoff: Duration::from_millis(10),
+                max_backoff: Duration::from_millis(20),
+            },
+            ..Default::default()
+        };
+        let _registered = register(host_of(&server), settings);
+
+        let resp = get(&server.uri()).await.unwrap();
+        assert_eq!("ok", resp.text().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn http_get_should_fail_after_retries_exhausted() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(500))
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let settings = HttpSettings {
+            retry: RetryPolicy {
+                max_retries: 1,
+                initial_backoff: Duration::from_millis(10),
+                max_backoff: Duration::from_millis(10),
+            },
+            ..Default::default()
+        };
+        let _registered = register(host_of(&server), settings);
+
+        assert!(get(&server.uri()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn http_get_should_time_out() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_millis(500)))
+            .mount(&server)
+            .await;
+
+        let settings = HttpSettings {
+            timeout: Some(Duration::from_millis(50)),
+            retry: RetryPolicy {
+                max_retries: 0,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let _registered = register(host_of(&server), settings);
+
+        assert!(get(&server.uri()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn http_stream_should_enforce_max_download_size() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("x".repeat(100)))
+            .mount(&server)
+            .await;
+
+        let settings = HttpSettings {
+            max_download_size: Some(64),
+            ..Default::default()
+        };
+        let _registered = register(host_of(&server), settings);
+
+        let resp = get(&server.uri()).await.unwrap();
+        let err = HttpStream::from_response(&server.uri(), resp)
+            .err()
+            .unwrap();
+        assert!(err
+            .to_string()
+            .contains("maximum download size of 64 bytes"));
+
+        // servers may not announce the length, the body is checked while it is read
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let host = listener.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            use tokio::io::{AsyncReadExt, AsyncWriteExt};
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = [0; 1024];
+            let _ = socket.read(&mut request).await.unwrap();
+            let body = "x".repeat(100);
+            let resp = format!("HTTP/1.1 200 OK\r\nConnection: close\r\n\r\n{}", body);
+            socket.write_all(resp.as_bytes()).await.unwrap();
+        });
+        let settings = HttpSettings {
+            max_download_size: Some(32),
+            ..Default::default()
+        };
+        let _unannounced = register(host.clone(), settings);
+
+        let url = format!("http://{}/data.csv", host);
+        let (_, stream) = HttpFetcher(&url).stream().await.unwrap();
+        let err = stream.collect().await.err().unwrap();
+        assert!(err
+            .to_string()
+            .contains("maximum download size of 32 bytes"));
+    }
+
+    #[test]
+    fn http_client_should_be_reused_until_settings_change() {
+        let url = Url::parse("http://reuse.test/a.csv").unwrap();
+        let _registered = register("reuse.test".to_string(), HttpSettings::default());
+        let (key, settings) = lookup_http_settings(&url);
+        assert_eq!("reuse.test", key);
+        client_for(&key, &settings).unwrap();
+        assert!(HTTP_CLIENTS.read().unwrap().contains_key("reuse.test"));
+
+        register_http_settings("reuse.test", HttpSettings::default());
+        assert!(!HTTP_CLIENTS.read().unwrap().contains_key("reuse.test"));
+    }
+
+    #[test]
+    fn retry_backoff_should_be_capped() {
+        let policy = RetryPolicy {
+            max_retries: 5,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(300),
+        };
+        assert_eq!(Duration::from_millis(100), policy.backoff(0));
+        assert_eq!(Duration::from_millis(200), policy.backoff(1));
+        assert_eq!(Duration::from_millis(300), policy.backoff(2));
+    }
+}
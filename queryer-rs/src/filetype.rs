@@ -1,15 +1,348 @@
+use crate::compression::Compression;
+
 #[derive(Debug, PartialEq, Eq)]
 #[non_exhaustive]
 pub enum Filetype {
     Unknown = 0,
     Csv = 1,
     Json = 2,
+    NdJson = 3,
+    Tsv = 4,
+    Parquet = 5,
+    Arrow = 6,
+    Excel = 7,
+    Avro = 8,
+    FixedWidth = 9,
+    Xml = 10,
+    Yaml = 11,
+    Toml = 12,
+    Log = 13,
+    Html = 14,
 }
 
+/// Only the first bytes of the data are inspected when sniffing
+const SNIFF_LEN: usize = 64 * 1024;
+const SNIFF_LINES: usize = 20;
+const PARQUET_MAGIC: &[u8] = b"PAR1";
+const ARROW_MAGIC: &[u8] = b"ARROW1";
+const AVRO_MAGIC: &[u8] = b"Obj\x01";
+/// Messages of the arrow ipc stream format start with a continuation marker
+const ARROW_CONTINUATION: &[u8] = &[0xff; 4];
+
 pub(crate) fn get_data_filetype(tp: Option<&str>) -> Filetype {
     match tp.unwrap_or("").to_lowercase().as_str() {
         "csv" => Filetype::Csv,
         "json" => Filetype::Json,
+        "tsv" | "tab" => Filetype::Tsv,
+        "ndjson" | "jsonl" => Filetype::NdJson,
+        "parquet" | "pq" => Filetype::Parquet,
+        "arrow" | "arrows" | "feather" | "ipc" => Filetype::Arrow,
+        "xlsx" | "xlsm" | "xlsb" | "xls" | "ods" => Filetype::Excel,
+        "avro" => Filetype::Avro,
+        "fwf" => Filetype::FixedWidth,
+        "xml" => Filetype::Xml,
+        "yaml" | "yml" => Filetype::Yaml,
+        "toml" => Filetype::Toml,
+        "log" => Filetype::Log,
+        "html" | "htm" => Filetype::Html,
         _ => Filetype::Unknown,
     }
 }
+
+/// A parsed `Content-Type` header value, e.g. `text/csv; charset=utf-8`
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct Mime {
+    /// lowercase `type/subtype` without parameters
+    pub(crate) essence: String,
+    pub(crate) charset: Option<String>,
+}
+
+impl Mime {
+    pub(crate) fn parse(value: &str) -> Option<Self> {
+        let mut parts = value.split(';');
+        let essence = parts.next()?.trim().to_lowercase();
+        let (tp, subtype) = essence.split_once('/')?;
+        if tp.is_empty() || subtype.is_empty() {
+            return None;
+        }
+
+        let charset = parts
+            .filter_map(|param| param.split_once('='))
+            .find(|(name, _)| name.trim().eq_ignore_ascii_case("charset"))
+            .map(|(_, v)| v.trim().trim_matches('"').to_lowercase());
+
+        Some(Self { essence, charset })
+    }
+
+    pub(crate) fn filetype(&self) -> Filetype {
+        match self.essence.as_str() {
+            "text/csv"
+            | "application/csv"
+            | "text/comma-separated-values"
+            | "text/x-csv"
+            | "application/x-csv" => Filetype::Csv,
+            "text/tab-separated-values" => Filetype::Tsv,
+            "application/x-ndjson"
+            | "application/ndjson"
+            | "application/jsonl"
+            | "application/x-jsonlines"
+            | "application/jsonlines" => Filetype::NdJson,
+            "application/json" | "text/json" | "application/x-json" => Filetype::Json,
+            "application/vnd.apache.parquet" | "application/x-parquet" => Filetype::Parquet,
+            "application/vnd.apache.arrow.file" | "application/vnd.apache.arrow.stream" => {
+                Filetype::Arrow
+            }
+            "application/vnd.ms-excel"
+            | "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"
+            | "application/vnd.oasis.opendocument.spreadsheet" => Filetype::Excel,
+            "application/avro" | "avro/binary" | "application/vnd.apache.avro+binary" => {
+                Filetype::Avro
+            }
+            "text/html" | "application/xhtml+xml" => Filetype::Html,
+            "application/xml" | "text/xml" => Filetype::Xml,
+            "application/yaml" | "application/x-yaml" | "text/yaml" | "text/x-yaml" => {
+                Filetype::Yaml
+            }
+            "application/toml" => Filetype::Toml,
+            // structured syntax suffix, e.g. application/ld+json, application/vnd.api+json
+            essence if essence.ends_with("+json") => Filetype::Json,
+            essence if essence.ends_with("+xml") => Filetype::Xml,
+            essence if essence.ends_with("+yaml") => Filetype::Yaml,
+            _ => Filetype::Unknown,
+        }
+    }
+
+    /// Charset to decode the body with, `None` if it already is utf-8
+    pub(crate) fn encoding(&self) -> Option<&'static encoding_rs::Encoding> {
+        self.charset
+            .as_deref()
+            .and_then(|label| encoding_rs::Encoding::for_label(label.as_bytes()))
+            .filter(|encoding| *encoding != encoding_rs::UTF_8)
+    }
+}
+
+/// Get filetype and compression from a file name or url, e.g. `data.csv.gz` is a gzip compressed csv
+pub(crate) fn detect_from_name(name: &str) -> (Filetype, Option<Compression>) {
+    let name = name.split(['?', '#']).next().unwrap_or_default();
+    let name = name.rsplit('/').next().unwrap_or_default();
+
+    let mut parts = name.rsplit('.');
+    let ext = parts.next();
+    match ext.and_then(Compression::from_extension) {
+        Some(compression) if name.contains('.') => {
+            (get_data_filetype(parts.next()), Some(compression))
+        }
+        _ if name.contains('.') => (get_data_filetype(ext), None),
+        _ => (Filetype::Unknown, None),
+    }
+}
+
+/// Guess the filetype from the first bytes of the data, used when neither
+/// the content type nor the file name tells us
+pub(crate) fn sniff(data: &[u8]) -> Filetype {
+    let sample = &data[..data.len().min(SNIFF_LEN)];
+    let sample = sample.strip_prefix(b"\xef\xbb\xbf").unwrap_or(sample);
+    let start = sample
+        .iter()
+        .position(|b| !b.is_ascii_whitespace())
+        .unwrap_or(sample.len());
+    let text = &sample[start..];
+
+    match text.first() {
+        Some(b'[') => Filetype::Json,
+        Some(b'<') if is_html(text) => Filetype::Html,
+        Some(b'<') => Filetype::Xml,
+        // the start of a yaml document
+        Some(b'-') if text.starts_with(b"---") => Filetype::Yaml,
+        Some(b'{') => {
+            // several lines each holding an object is newline delimited json
+            let mut lines = text
+                .split(|b| *b == b'\n')
+                .map(|l| l.trim_ascii())
+                .filter(|l| !l.is_empty());
+            match (lines.next(), lines.next()) {
+                (Some(first), Some(second))
+                    if first.ends_with(b"}") && second.starts_with(b"{") =>
+                {
+                    Filetype::NdJson
+                }
+                _ => Filetype::Json,
+            }
+        }
+        _ => match sniff_delimiter(text, data.len() > sample.len(), None) {
+            Some(b',') => Filetype::Csv,
+            Some(b'\t') => Filetype::Tsv,
+            _ => Filetype::Unknown,
+        },
+    }
+}
+
+/// Pages start with a doctype or an `<html>` element, possibly after an xml
+/// declaration or comments
+fn is_html(text: &[u8]) -> bool {
+    let text = text.to_ascii_lowercase();
+    text.starts_with(b"<!doctype html") || text.windows(5).any(|w| w == b"<html")
+}
+
+/// Parquet files start and end with `PAR1`
+pub(crate) fn is_parquet(data: &[u8]) -> bool {
+    data.len() >= 2 * PARQUET_MAGIC.len()
+        && data.starts_with(PARQUET_MAGIC)
+        && data.ends_with(PARQUET_MAGIC)
+}
+
+/// Arrow ipc files start with `ARROW1`, streams with a continuation marker
+pub(crate) fn is_arrow(data: &[u8]) -> bool {
+    data.starts_with(ARROW_MAGIC) || data.starts_with(ARROW_CONTINUATION)
+}
+
+/// Avro container files start with `Obj` and the format version
+pub(crate) fn is_avro(data: &[u8]) -> bool {
+    data.starts_with(AVRO_MAGIC)
+}
+
+/// Delimiter of csv like text, `None` if it can't be told. Lines starting with
+/// `comment` are not looked at.
+pub(crate) fn detect_delimiter(data: &[u8], comment: Option<u8>) -> Option<u8> {
+    let sample = &data[..data.len().min(SNIFF_LEN)];
+    sniff_delimiter(sample, data.len() > sample.len(), comment)
+}
+
+/// Pick the delimiter that appears the same number of times (outside of quotes)
+/// on every line of the sample, preferring the most frequent one
+fn sniff_delimiter(text: &[u8], truncated: bool, comment: Option<u8>) -> Option<u8> {
+    if std::str::from_utf8(text).is_err() && !truncated {
+        return None;
+    }
+
+    let mut lines: Vec<&[u8]> = text
+        .split(|b| *b == b'\n')
+        .filter(|l| !l.trim_ascii().is_empty())
+        .filter(|l| comment.is_none() || l.first() != comment.as_ref())
+        .collect();
+    // the last line of a truncated sample is likely incomplete
+    if truncated && lines.len() > 1 {
+        lines.pop();
+    }
+    lines.truncate(SNIFF_LINES);
+
+    [b',', b'\t', b';', b'|']
+        .into_iter()
+        .filter_map(|delimiter| {
+            let mut counts = lines.iter().map(|l| count_unquoted(l, delimiter));
+            let first = counts.next()?;
+            (first > 0 && counts.all(|c| c == first)).then_some((first, delimiter))
+        })
+        .max_by_key(|(count, _)| *count)
+        .map(|(_, delimiter)| delimiter)
+}
+
+fn count_unquoted(line: &[u8], delimiter: u8) -> usize {
+    let mut quoted = false;
+    line.iter()
+        .filter(|b| {
+            if **b == b'"' {
+                quoted = !quoted;
+            }
+            !quoted && **b == delimiter
+        })
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detect_from_name_should_work() {
+        assert_eq!((Filetype::Csv, None), detect_from_name("./a/data.csv"));
+        assert_eq!(
+            (Filetype::Csv, Some(Compression::Gzip)),
+            detect_from_name("host/data.CSV.gz?token=1")
+        );
+        assert_eq!(
+            (Filetype::Unknown, Some(Compression::Zip)),
+            detect_from_name("x.zip")
+        );
+        assert_eq!((Filetype::Unknown, None), detect_from_name("host/export"));
+        assert_eq!(
+            (Filetype::NdJson, Some(Compression::Gzip)),
+            detect_from_name("logs/app.jsonl.gz")
+        );
+        assert_eq!(
+            (Filetype::Excel, None),
+            detect_from_name("report.xlsx#Sheet2")
+        );
+        assert_eq!(
+            (Filetype::Parquet, None),
+            detect_from_name("s3/part-0.parquet")
+        );
+        assert_eq!((Filetype::Yaml, None), detect_from_name("k8s/services.yml"));
+        assert_eq!((Filetype::Toml, None), detect_from_name("flags.toml"));
+        assert_eq!(
+            (Filetype::Log, Some(Compression::Gzip)),
+            detect_from_name("nginx/access.log.gz")
+        );
+    }
+
+    #[test]
+    fn mime_should_map_to_filetype() {
+        let mime = Mime::parse("Text/CSV; charset=\"ISO-8859-1\"").unwrap();
+        assert_eq!("text/csv", mime.essence);
+        assert_eq!(Some("iso-8859-1".to_string()), mime.charset);
+        assert_eq!(Filetype::Csv, mime.filetype());
+        assert_eq!(Some(encoding_rs::WINDOWS_1252), mime.encoding());
+
+        let cases = [
+            ("application/json", Filetype::Json),
+            ("application/ld+json; profile=x", Filetype::Json),
+            ("application/x-ndjson", Filetype::NdJson),
+            ("application/atom+xml", Filetype::Xml),
+            ("application/yaml", Filetype::Yaml),
+            ("application/toml", Filetype::Toml),
+            ("application/vnd.ms-excel", Filetype::Excel),
+            ("text/tab-separated-values", Filetype::Tsv),
+            ("application/vnd.apache.parquet", Filetype::Parquet),
+            ("application/vnd.apache.arrow.stream", Filetype::Arrow),
+            ("text/plain; charset=utf-8", Filetype::Unknown),
+        ];
+        for (value, tp) in cases {
+            assert_eq!(tp, Mime::parse(value).unwrap().filetype(), "{}", value);
+        }
+        assert_eq!(
+            None,
+            Mime::parse("text/csv; charset=utf-8").unwrap().encoding()
+        );
+        assert_eq!(None, Mime::parse("csv"));
+    }
+
+    #[test]
+    fn sniff_should_work() {
+        assert_eq!(Filetype::Json, sniff(b"\n  [{\"a\": 1}]"));
+        assert_eq!(Filetype::Json, sniff(b"{\"data\": {\"a\": 1}}"));
+        assert_eq!(Filetype::NdJson, sniff(b"{\"a\": 1}\n\n{\"a\": 2}\n"));
+        assert_eq!(Filetype::Csv, sniff(b"a,b,\"c,d\"\n1,2,3\n4,5,6"));
+        assert_eq!(Filetype::Tsv, sniff(b"a\tb\n1\t2,5\n"));
+        assert_eq!(
+            Some(b';'),
+            detect_delimiter(b"# a,b\na;b\n1,5;2\n", Some(b'#'))
+        );
+        assert_eq!(Some(b'|'), detect_delimiter(b"a|b|c\n1|2|3\n", None));
+        assert_eq!(
+            Filetype::Xml,
+            sniff(b"\xef\xbb\xbf<?xml version=\"1.0\"?><a/>")
+        );
+        assert_eq!(Filetype::Yaml, sniff(b"---\n- a: 1\n"));
+        assert_eq!(
+            Filetype::Html,
+            sniff(b"<!DOCTYPE html>\n<html><table></table></html>")
+        );
+        assert_eq!(Filetype::Xml, sniff(b"<rss><item>html</item></rss>"));
+        assert_eq!(Filetype::Unknown, sniff(b"hello world"));
+        assert_eq!(Filetype::Unknown, sniff(b""));
+        assert!(is_parquet(b"PAR1....PAR1"));
+        assert!(is_arrow(b"ARROW1\0\0...."));
+        assert!(!is_arrow(b"a,b\n"));
+        assert!(is_avro(b"Obj\x01\x04"));
+    }
+}
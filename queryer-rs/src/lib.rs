@@ -8,11 +8,19 @@ use std::{
 use tracing::info;
 
 use crate::{
-    ast_convert::Sql, dialect::SqlDialect, fetcher::retrieve_data, transformer::detect_content,
+    ast_convert::{resolve_nested, SourceArgs, Sql, Unnest},
+    database::{evaluates_condition, DatabaseSource},
+    dialect::{rewrite_accessors, SqlDialect},
+    fetcher::{retrieve, ApiFetcher, Fetch, SourceData},
+    transformer::{detect_content, transform_ranges, transform_stream, ReaderOptions},
 };
 
 mod ast_convert;
+mod column;
+mod compression;
+mod database;
 mod dialect;
+mod document;
 pub mod fetcher;
 pub mod filetype;
 mod transformer;
@@ -48,6 +56,13 @@ impl DataSet {
         Ok(String::from_utf8(buf)?)
     }
 
+    /// Write the data in the arrow ipc file format, readable by any arrow implementation
+    pub fn to_arrow_ipc(&mut self) -> Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        IpcWriter::new(&mut buf).finish(&mut self.0)?;
+        Ok(buf)
+    }
+
     pub fn to_json(&mut self) -> Result<String> {
         let mut buf = Vec::new();
         let writer = JsonWriter::new(&mut buf);
@@ -66,7 +81,14 @@ impl DataSet {
 }
 
 pub async fn query(sql: impl AsRef<str>) -> Result<DataSet> {
-    let ast = Parser::parse_sql(&SqlDialect, sql.as_ref())?;
+    query_with_options(sql, &[]).await
+}
+
+/// Like [`query`], with reader options for file and http sources given as the
+/// `name => value` pairs of SQL, e.g. `[("infer_schema_length", "all")]`.
+/// Options given in the query take precedence.
+pub async fn query_with_options(sql: impl AsRef<str>, options: &[(&str, &str)]) -> Result<DataSet> {
+    let ast = Parser::parse_sql(&SqlDialect, &rewrite_accessors(sql.as_ref())?)?;
 
     if ast.len() != 1 {
         return Err(anyhow!("only support single sql"));
@@ -76,35 +98,125 @@ pub async fn query(sql: impl AsRef<str>) -> Result<DataSet> {
 
     let Sql {
         source,
-        condition,
+        args,
+        unnest,
+        mut condition,
         selection,
+        group_by,
+        having,
         offset,
         limit,
         order_by,
+        pushdown,
     } = sql.try_into()?;
 
     info!("retrieving data from source: {}", source);
 
-    let ds = detect_content(
-        retrieve_data(source)
-            .await
-            .context("failed to retrieve data")?,
-    )?
-    .transform()?;
+    let ds = match DatabaseSource::parse(source)? {
+        Some(_) if !args.is_empty() => return Err(anyhow!("{} does not take arguments", source)),
+        Some(db) => {
+            if evaluates_condition(&pushdown) {
+                condition = None;
+            }
+            db.load(&pushdown)
+                .await
+                .context("failed to load data from database")?
+        }
+        None if source.eq_ignore_ascii_case("read_api") || source.starts_with("api+http") => {
+            let (fetcher, args) = ApiFetcher::from_args(source, &args)?;
+            let options = ReaderOptions::from_args(&with_options(args, options), None)?;
+            detect_content(fetcher.fetch().await?)?.transform_with(&options, &pushdown)?
+        }
+        None => {
+            let args = with_options(args, options);
+            let mut options =
+                ReaderOptions::from_args(&args, source.split_once('#').map(|(_, f)| f))?
+                    .load_layout()
+                    .await?;
+            match retrieve(source).await.context("failed to retrieve data")? {
+                SourceData::Bytes(file_type, data, entry) => {
+                    // a fragment which picked the zip entry does not name a sheet
+                    if entry.is_some() && options.sheet == entry {
+                        options.sheet = None;
+                    }
+                    detect_content((options.file_type(file_type), data))?
+                        .transform_with(&options, &pushdown)?
+                }
+                SourceData::Stream(file_type, stream) => {
+                    transform_stream(file_type, *stream, &options).await?
+                }
+                SourceData::Ranges(file_type, reader) => {
+                    transform_ranges(file_type, *reader, &options, &pushdown).await?
+                }
+            }
+        }
+    };
+
+    let mut lazy = ds.0.lazy();
+    for Unnest { column, alias } in unnest {
+        let name = alias.unwrap_or_else(|| column.clone());
+        let items = resolve_nested(col(&column), &*lazy.schema()?).alias(&name);
+        // like a CROSS JOIN, rows with empty or null lists produce no rows
+        lazy = lazy
+            .with_column(items)
+            .filter(col(&name).arr().lengths().gt(lit(0)))
+            .explode([col(&name)]);
+    }
+
+    let schema = lazy.schema()?;
+    let mut selection: Vec<_> = selection
+        .into_iter()
+        .map(|expr| resolve_nested(expr, &schema))
+        .collect();
 
     let mut filtered = match condition {
-        Some(expr) => ds.0.lazy().filter(expr),
-        None => ds.0.lazy(),
+        Some(expr) => lazy.filter(resolve_nested(expr, &schema)),
+        None => lazy,
     };
 
-    filtered = order_by.into_iter().fold(filtered, |acc, (col, desc)| {
-        acc.sort(
-            &col,
-            SortOptions {
-                descending: desc,
-                nulls_last: false,
-            },
-        )
+    // the aggregates of the selection are computed per group, which leaves the
+    // keys and the aggregated columns to select, sort and slice
+    if !group_by.is_empty() {
+        let keys: Vec<_> = group_by
+            .into_iter()
+            .map(|expr| resolve_nested(expr, &schema))
+            .collect();
+        let mut aggregates: Vec<_> = selection
+            .iter()
+            .filter(|e| is_aggregate(e))
+            .cloned()
+            .collect();
+        // aggregates of HAVING get names of their own, they may differ from the
+        // selection's under the same name, e.g. `sum(a) AS count ... HAVING count(*) > 1`
+        let having = having.map(|expr| {
+            let mut expr = resolve_nested(expr, &schema);
+            expr.mutate().apply(|e| {
+                if let Expr::Alias(inner, _) = e {
+                    if is_aggregate(inner) {
+                        let name = format!("__having_{}", aggregates.len());
+                        aggregates.push(inner.as_ref().clone().alias(&name));
+                        *e = col(&name);
+                    }
+                }
+                true
+            });
+            expr
+        });
+        filtered = filtered.groupby_stable(keys).agg(aggregates);
+        if let Some(expr) = having {
+            filtered = filtered.filter(expr);
+        }
+        selection = selection
+            .into_iter()
+            .map(|expr| match expr {
+                Expr::Alias(_, name) if is_aggregate(&expr) => col(&name),
+                expr => expr,
+            })
+            .collect();
+    }
+
+    filtered = order_by.into_iter().fold(filtered, |acc, (name, desc)| {
+        acc.sort_by_exprs([resolve_nested(col(&name), &schema)], [desc], false)
     });
 
     if offset.is_some() || limit.is_some() {
@@ -113,3 +225,22 @@ pub async fn query(sql: impl AsRef<str>) -> Result<DataSet> {
 
     Ok(DataSet(filtered.select(selection).collect()?))
 }
+
+/// The reader `options` of the query, followed by the arguments of the source which
+/// take precedence
+fn with_options(args: SourceArgs, options: &[(&str, &str)]) -> SourceArgs {
+    SourceArgs {
+        named: options
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .chain(args.named)
+            .collect(),
+        ..args
+    }
+}
+
+/// Whether `expr` combines the rows of a group, e.g. `count(*)` or `sum(a)`
+fn is_aggregate(expr: &Expr) -> bool {
+    expr.into_iter()
+        .any(|e| matches!(e, Expr::Agg(_) | Expr::Count))
+}
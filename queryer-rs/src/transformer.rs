@@ -1,70 +1,817 @@
 use anyhow::{anyhow, Result};
-use polars::prelude::*;
+use bytes::{Bytes, BytesMut};
+use percent_encoding::percent_decode_str;
+use polars::{io::avro::AvroReader, prelude::*};
 use std::io::Cursor;
 
-use crate::{filetype, DataSet};
+use crate::{
+    ast_convert::{Pushdown, SourceArgs},
+    document::Document,
+    fetcher::{HttpRangeReader, HttpStream},
+    filetype, DataSet,
+};
 
-pub trait Transform {
-    type Error;
-    fn transform(self) -> Result<DataSet, Self::Error>;
-}
+use self::{
+    excel::read_excel,
+    fixed_width::{load_layout, parse_columns, read_fixed_width, FixedColumn},
+    html::read_html,
+    log::read_log,
+    parquet::{read_parquet, read_remote_parquet},
+    schema::{apply_schema, parse_schema, ColumnType},
+    xml::read_xml,
+    yaml_toml::{read_toml, read_yaml},
+};
+
+mod excel;
+mod fixed_width;
+mod html;
+mod log;
+mod parquet;
+mod schema;
+mod xml;
+mod yaml_toml;
 
 #[derive(Debug)]
 pub enum Transformer {
     Csv(CsvTransformer),
     Json(JsonTransformer),
+    NdJson(NdJsonTransformer),
+    Tsv(TsvTransformer),
+    Parquet(ParquetTransformer),
+    Arrow(ArrowTransformer),
+    Excel(ExcelTransformer),
+    Avro(AvroTransformer),
+    FixedWidth(FixedWidthTransformer),
+    Xml(XmlTransformer),
+    Yaml(YamlTransformer),
+    Toml(TomlTransformer),
+    Log(LogTransformer),
+    Html(HtmlTransformer),
 }
 
 #[derive(Default, Debug)]
-pub struct CsvTransformer(pub(crate) String);
+pub struct CsvTransformer(pub(crate) Bytes);
+
+#[derive(Default, Debug)]
+pub struct JsonTransformer(pub(crate) Bytes);
+
+#[derive(Default, Debug)]
+pub struct NdJsonTransformer(pub(crate) Bytes);
+
+#[derive(Default, Debug)]
+pub struct TsvTransformer(pub(crate) Bytes);
+
+#[derive(Default, Debug)]
+pub struct ParquetTransformer(pub(crate) Bytes);
+
+/// Arrow ipc data, in the file (feather v2) or the stream format
+#[derive(Default, Debug)]
+pub struct ArrowTransformer(pub(crate) Bytes);
+
+#[derive(Default, Debug)]
+pub struct ExcelTransformer(pub(crate) Bytes);
+
+#[derive(Default, Debug)]
+pub struct AvroTransformer(pub(crate) Bytes);
+
+/// Text with values at fixed positions, cut by the columns of the reader options
+#[derive(Default, Debug)]
+pub struct FixedWidthTransformer(pub(crate) Bytes);
+
+#[derive(Default, Debug)]
+pub struct XmlTransformer(pub(crate) Bytes);
+
+#[derive(Default, Debug)]
+pub struct YamlTransformer(pub(crate) Bytes);
+
+#[derive(Default, Debug)]
+pub struct TomlTransformer(pub(crate) Bytes);
+
+#[derive(Default, Debug)]
+pub struct LogTransformer(pub(crate) Bytes);
 
 #[derive(Default, Debug)]
-pub struct JsonTransformer(pub(crate) String);
+pub struct HtmlTransformer(pub(crate) Bytes);
+
+/// How to read the data of a source, given as arguments of the source,
+/// e.g. `FROM file://resp.json(root => '/data/items')`
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub(crate) struct ReaderOptions {
+    /// JSON pointer (`/data/items`) or JSONPath (`$.data.items`) of the records array,
+    /// for XML the path of the record elements (`/feed/entry`), for YAML and TOML
+    /// the sequence of records
+    pub(crate) json_root: Option<String>,
+    /// replace struct columns by a column per field, named `a_b_c`
+    pub(crate) flatten: bool,
+    /// number of records the schema is inferred from, the reader's default if not
+    /// given, all of them if 0
+    pub(crate) infer_schema_length: Option<usize>,
+    /// types of some or all columns, overriding the inferred ones
+    pub(crate) schema: Vec<ColumnType>,
+    /// text values read as null, e.g. `NA`
+    pub(crate) null_values: Vec<String>,
+    /// skip malformed records instead of failing
+    pub(crate) ignore_errors: bool,
+    /// sheet of a workbook, the first one if not given
+    pub(crate) sheet: Option<String>,
+    /// cells of a sheet to read, e.g. `B2:D10`
+    pub(crate) range: Option<String>,
+    /// rows before the header row
+    pub(crate) skip_rows: usize,
+    /// delimiter of csv like text, detected if not given
+    pub(crate) delimiter: Option<u8>,
+    /// `"` if not given
+    pub(crate) quote_char: Option<u8>,
+    /// escapes quotes in quoted fields, e.g. `\"`, instead of doubling them
+    pub(crate) escape_char: Option<u8>,
+    /// lines starting with it are skipped
+    pub(crate) comment_char: Option<u8>,
+    /// columns of fixed width text, given directly or by a layout file
+    pub(crate) fixed_width: Option<Vec<FixedColumn>>,
+    /// source of a csv file describing the columns of fixed width text
+    pub(crate) layout: Option<String>,
+    /// `common`, `combined` or a regex with a named group per column
+    pub(crate) log_format: Option<String>,
+    /// table of an html page, its number or a css selector, the first one if not given
+    pub(crate) table: Option<String>,
+}
+
+impl ReaderOptions {
+    /// A fragment starting with `/` is a JSON pointer, e.g. `file://resp.json#/data/items`,
+    /// fragments of `name=value` pairs joined by `&` are arguments, e.g.
+    /// `https://example.org/stats.html#table=2`, other fragments name the sheet of a
+    /// workbook, e.g. `file://report.xlsx#Sheet2`, unless they picked the entry of a zip
+    /// archive
+    pub(crate) fn from_args(args: &SourceArgs, fragment: Option<&str>) -> Result<Self> {
+        if let Some(arg) = args.positional.first() {
+            return Err(anyhow!("unexpected argument {}, use name => value", arg));
+        }
+
+        let mut named = Vec::new();
+        let (json_root, sheet) = match fragment {
+            Some(f) if f.starts_with('/') => (Some(f.to_string()), None),
+            Some(f) if f.contains('=') => {
+                for pair in f.split('&').filter(|p| !p.is_empty()) {
+                    let (name, value) = pair
+                        .split_once('=')
+                        .ok_or(anyhow!("argument {} has no value", pair))?;
+                    let value = percent_decode_str(value).decode_utf8()?;
+                    named.push((name.to_string(), value.into_owned()));
+                }
+                (None, None)
+            }
+            Some(f) => (None, Some(f.to_string())),
+            None => (None, None),
+        };
+        let mut options = Self {
+            json_root,
+            sheet,
+            ..Default::default()
+        };
+        for (name, value) in named.iter().chain(&args.named) {
+            match name.to_lowercase().as_str() {
+                "root" => options.json_root = Some(value.clone()),
+                "flatten" => options.flatten = value.parse()?,
+                "infer_schema_length" => {
+                    options.infer_schema_length = match value.eq_ignore_ascii_case("all") {
+                        true => Some(0),
+                        false => Some(value.parse()?),
+                    }
+                }
+                "schema" => options.schema = parse_schema(value)?,
+                "null_values" => {
+                    options.null_values = value.split(',').map(|v| v.trim().to_string()).collect()
+                }
+                "ignore_errors" => options.ignore_errors = value.parse()?,
+                "sheet" => options.sheet = Some(value.clone()),
+                "range" => options.range = Some(value.clone()),
+                "skip_rows" => options.skip_rows = value.parse()?,
+                "delimiter" => options.delimiter = Some(ascii_char(name, value)?),
+                "quote" => options.quote_char = Some(ascii_char(name, value)?),
+                "escape" => options.escape_char = Some(ascii_char(name, value)?),
+                "comment" => options.comment_char = Some(ascii_char(name, value)?),
+                "columns" => options.fixed_width = Some(parse_columns(value)?),
+                "layout" => options.layout = Some(value.clone()),
+                "log_format" => options.log_format = Some(value.clone()),
+                "table" => options.table = Some(value.clone()),
+                _ => return Err(anyhow!("unknown argument {}", name)),
+            }
+        }
+        Ok(options)
+    }
+
+    /// Read the columns of fixed width text from the layout file, if one is given
+    pub(crate) async fn load_layout(mut self) -> Result<Self> {
+        if let Some(layout) = &self.layout {
+            self.fixed_width = Some(load_layout(layout).await?);
+        }
+        Ok(self)
+    }
+
+    /// The file type to read the data as, the detected one unless fixed width
+    /// columns or a log format are given
+    pub(crate) fn file_type(&self, detected: filetype::Filetype) -> filetype::Filetype {
+        match (&self.fixed_width, &self.log_format) {
+            (Some(_), _) => filetype::Filetype::FixedWidth,
+            (None, Some(_)) => filetype::Filetype::Log,
+            (None, None) => detected,
+        }
+    }
+
+    /// The pushdown for the readers: once flattened a query column `a_b_c` may come
+    /// from the root column `a`, `a_b` or `a_b_c`, so all of those are read
+    fn read_pushdown<'a>(&self, pushdown: &Pushdown<'a>) -> Pushdown<'a> {
+        let columns = match (&pushdown.columns, self.flatten) {
+            (Some(columns), true) => Some(
+                columns
+                    .iter()
+                    .flat_map(|c| c.match_indices('_').map(|(i, _)| c[..i].to_string()))
+                    .chain(columns.iter().cloned())
+                    .collect(),
+            ),
+            (columns, _) => columns.clone(),
+        };
+        Pushdown {
+            columns,
+            ..*pushdown
+        }
+    }
+
+    /// Number of records to infer the schema from, `None` for all of them
+    fn infer_length(&self, default: usize) -> Option<usize> {
+        match self.infer_schema_length {
+            Some(0) => None,
+            Some(n) => Some(n),
+            None => Some(default),
+        }
+    }
+
+    /// The types of the schema columns to read csv as, dates and times are parsed
+    /// once read
+    fn csv_dtypes(&self) -> Option<Schema> {
+        (!self.schema.is_empty()).then(|| {
+            self.schema
+                .iter()
+                .map(|c| Field::new(&c.name, c.read_as()))
+                .collect()
+        })
+    }
+
+    fn quote(&self) -> u8 {
+        self.quote_char.unwrap_or(b'"')
+    }
+
+    /// The given delimiter, or the one of csv `data` after the rows to skip
+    fn delimiter(&self, file_type: &filetype::Filetype, data: &[u8]) -> u8 {
+        match (self.delimiter, file_type) {
+            (Some(delimiter), _) => delimiter,
+            (None, filetype::Filetype::Tsv) => b'\t',
+            (None, _) => {
+                let mut text = data;
+                for _ in 0..self.skip_rows {
+                    let end = text.iter().position(|b| *b == b'\n');
+                    text = end.map_or(&[], |end| &text[end + 1..]);
+                }
+                filetype::detect_delimiter(text, self.comment_char).unwrap_or(b',')
+            }
+        }
+    }
+
+    /// A csv reader for `data` with the quote, escape and comment characters and the
+    /// null values
+    fn csv_reader<'a>(&self, data: Bytes, delimiter: u8) -> CsvReader<'a, Cursor<Bytes>> {
+        let data = match self.escape_char {
+            Some(escape) if escape != self.quote() => unescape(&data, escape, self.quote()),
+            _ => data,
+        };
+        CsvReader::new(Cursor::new(data))
+            .with_delimiter(delimiter)
+            .with_quote_char(Some(self.quote()))
+            .with_comment_char(self.comment_char)
+            .with_null_values(
+                (!self.null_values.is_empty())
+                    .then(|| NullValues::AllColumns(self.null_values.clone())),
+            )
+    }
+}
+
+/// A single ascii character given as argument, `\t` stands for a tab
+fn ascii_char(name: &str, value: &str) -> Result<u8> {
+    match value.as_bytes() {
+        b"\\t" | b"tab" => Ok(b'\t'),
+        [c] if c.is_ascii() => Ok(*c),
+        _ => Err(anyhow!(
+            "{} must be a single character, got {}",
+            name,
+            value
+        )),
+    }
+}
+
+/// Turn escaped quotes (`\"`) in quoted fields into doubled ones (`""`) as the csv
+/// parser expects, other escaped characters in quoted fields are kept without the
+/// escape. Outside of quoted fields the escape character is an ordinary one.
+fn unescape(data: &[u8], escape: u8, quote: u8) -> Bytes {
+    let mut out = BytesMut::with_capacity(data.len());
+    let mut quoted = false;
+    let mut bytes = data.iter();
+    while let Some(&b) = bytes.next() {
+        match bytes.as_slice().first() {
+            Some(&next) if quoted && b == escape => {
+                match next == quote {
+                    true => out.extend_from_slice(&[quote, quote]),
+                    false => out.extend_from_slice(&[next]),
+                }
+                bytes.next();
+            }
+            _ => {
+                quoted ^= b == quote;
+                out.extend_from_slice(&[b]);
+            }
+        }
+    }
+    out.freeze()
+}
 
 impl Transformer {
-    pub fn transform(self) -> Result<DataSet> {
-        match self {
-            Transformer::Csv(csv) => csv.transform(),
-            Transformer::Json(json) => json.transform(),
+    /// Read the data with the default options
+    #[cfg(test)]
+    pub(crate) fn transform(self) -> Result<DataSet> {
+        self.transform_with(&ReaderOptions::default(), &Pushdown::default())
+    }
+
+    /// Read the data with `options`. Formats which can skip data use `pushdown`
+    /// to decode only what the query needs.
+    pub(crate) fn transform_with(
+        self,
+        options: &ReaderOptions,
+        pushdown: &Pushdown,
+    ) -> Result<DataSet> {
+        let read = options.read_pushdown(pushdown);
+        let ds = match self {
+            Transformer::Json(json) if options.json_root.is_some() => {
+                let root = options.json_root.as_deref().unwrap_or_default();
+                read_json(select_json_root(json.0, root)?, options)
+            }
+            Transformer::Csv(csv) => read_delimited(csv.0, filetype::Filetype::Csv, options),
+            Transformer::Json(json) => read_json(json.0, options),
+            Transformer::NdJson(json) => read_ndjson(json.0, options),
+            Transformer::Tsv(tsv) => read_delimited(tsv.0, filetype::Filetype::Tsv, options),
+            Transformer::Parquet(parquet) => read_parquet(parquet.0, &read),
+            Transformer::Arrow(arrow) => read_arrow(arrow.0, &read),
+            Transformer::Excel(excel) => read_excel(excel.0, options),
+            Transformer::Avro(avro) => read_avro(avro.0, &read),
+            Transformer::FixedWidth(text) => read_fixed_width(text.0, options),
+            Transformer::Xml(xml) => read_xml(xml.0, options.json_root.as_deref()),
+            Transformer::Yaml(yaml) => read_yaml(yaml.0, options.json_root.as_deref()),
+            Transformer::Toml(toml) => read_toml(toml.0, options.json_root.as_deref()),
+            Transformer::Log(log) => read_log(log.0, options),
+            Transformer::Html(html) => read_html(html.0, options),
+        }?;
+        apply_options(ds, options, pushdown)
+    }
+}
+
+/// Give the columns their types and flatten structs as `options` ask
+fn apply_options(ds: DataSet, options: &ReaderOptions, pushdown: &Pushdown) -> Result<DataSet> {
+    // columns left out by the query are not read by formats supporting pushdown
+    let schema: Vec<_> = options
+        .schema
+        .iter()
+        .filter(|c| match (&pushdown.columns, ds.column(&c.name)) {
+            (Some(columns), Err(_)) => columns.contains(&c.name),
+            _ => true,
+        })
+        .cloned()
+        .collect();
+    let df = apply_schema(ds.0, &schema, &options.null_values)?;
+    match options.flatten {
+        true => Ok(DataSet(flatten(df)?)),
+        false => Ok(DataSet(df)),
+    }
+}
+
+/// Replace struct columns, also nested ones, by a column per field named `a_b_c`
+fn flatten(df: DataFrame) -> Result<DataFrame> {
+    fn collect(s: Series, prefix: &str, out: &mut Vec<Series>) -> Result<()> {
+        match s.dtype() {
+            DataType::Struct(_) => {
+                for field in s.struct_()?.fields() {
+                    let name = format!("{}{}_", prefix, field.name());
+                    collect(field.clone(), &name, out)?;
+                }
+            }
+            _ => {
+                let mut s = s;
+                s.rename(prefix.trim_end_matches('_'));
+                out.push(s);
+            }
         }
+        Ok(())
     }
+
+    let mut columns = Vec::with_capacity(df.width());
+    for s in df.get_columns() {
+        collect(s.clone(), &format!("{}_", s.name()), &mut columns)?;
+    }
+    Ok(DataFrame::new(columns)?)
 }
 
-pub fn detect_content(tup: (filetype::Filetype, String)) -> Result<Transformer> {
+pub fn detect_content(tup: (filetype::Filetype, Bytes)) -> Result<Transformer> {
     match tup.0 {
         filetype::Filetype::Csv => Ok(Transformer::Csv(CsvTransformer(tup.1))),
         filetype::Filetype::Json => Ok(Transformer::Json(JsonTransformer(tup.1))),
-        _ => Err(anyhow!("not support filetype")),
+        filetype::Filetype::NdJson => Ok(Transformer::NdJson(NdJsonTransformer(tup.1))),
+        filetype::Filetype::Tsv => Ok(Transformer::Tsv(TsvTransformer(tup.1))),
+        filetype::Filetype::Parquet => Ok(Transformer::Parquet(ParquetTransformer(tup.1))),
+        filetype::Filetype::Arrow => Ok(Transformer::Arrow(ArrowTransformer(tup.1))),
+        filetype::Filetype::Excel => Ok(Transformer::Excel(ExcelTransformer(tup.1))),
+        filetype::Filetype::Avro => Ok(Transformer::Avro(AvroTransformer(tup.1))),
+        filetype::Filetype::FixedWidth => Ok(Transformer::FixedWidth(FixedWidthTransformer(tup.1))),
+        filetype::Filetype::Xml => Ok(Transformer::Xml(XmlTransformer(tup.1))),
+        filetype::Filetype::Yaml => Ok(Transformer::Yaml(YamlTransformer(tup.1))),
+        filetype::Filetype::Toml => Ok(Transformer::Toml(TomlTransformer(tup.1))),
+        filetype::Filetype::Log => Ok(Transformer::Log(LogTransformer(tup.1))),
+        filetype::Filetype::Html => Ok(Transformer::Html(HtmlTransformer(tup.1))),
+        // last resort, look at the data itself
+        filetype::Filetype::Unknown => match filetype::sniff(&tup.1) {
+            filetype::Filetype::Unknown if filetype::is_parquet(&tup.1) => {
+                detect_content((filetype::Filetype::Parquet, tup.1))
+            }
+            filetype::Filetype::Unknown if filetype::is_arrow(&tup.1) => {
+                detect_content((filetype::Filetype::Arrow, tup.1))
+            }
+            filetype::Filetype::Unknown if filetype::is_avro(&tup.1) => {
+                detect_content((filetype::Filetype::Avro, tup.1))
+            }
+            filetype::Filetype::Unknown => Err(anyhow!("not support filetype")),
+            tp => detect_content((tp, tup.1)),
+        },
     }
 }
 
-impl Transform for CsvTransformer {
-    type Error = anyhow::Error;
+/// Pick the records array at `root` out of a JSON document
+fn select_json_root(data: Bytes, root: &str) -> Result<Bytes> {
+    let pointer = json_pointer(root)?;
+    let mut value: Document = serde_json::from_slice(&data)?;
+    let records = value
+        .pointer_mut(&pointer)
+        .map(Document::take)
+        .ok_or(anyhow!("{} not found in json", root))?;
 
-    fn transform(self) -> Result<DataSet, Self::Error> {
-        let df = CsvReader::new(Cursor::new(self.0))
-            .infer_schema(Some(16))
-            .finish()?;
-        Ok(DataSet(df))
+    let records = match records {
+        Document::Array(_) => records,
+        Document::Object(_) => Document::Array(vec![records]),
+        _ => return Err(anyhow!("{} is not an array of records", root)),
+    };
+    Ok(serde_json::to_vec(&records)?.into())
+}
+
+/// Append a csv record, empty values are left unquoted so they are read as nulls
+fn push_record<'a>(csv: &mut String, values: impl Iterator<Item = &'a str>) {
+    for (i, value) in values.enumerate() {
+        if i > 0 {
+            csv.push(',');
+        }
+        if !value.is_empty() {
+            csv.push('"');
+            csv.push_str(&value.replace('"', "\"\""));
+            csv.push('"');
+        }
     }
+    csv.push('\n');
 }
 
-impl Transform for JsonTransformer {
-    type Error = anyhow::Error;
+/// Turn a JSONPath like `$.data['items'][0]` into the JSON pointer `/data/items/0`,
+/// JSON pointers are returned as is
+fn json_pointer(path: &str) -> Result<String> {
+    let Some(mut rest) = path.strip_prefix('$') else {
+        return match path.is_empty() || path.starts_with('/') {
+            true => Ok(path.to_string()),
+            false => Err(anyhow!("{} is neither a JSON pointer nor a JSONPath", path)),
+        };
+    };
+
+    let mut pointer = String::new();
+    while !rest.is_empty() {
+        let (segment, next) = if let Some(r) = rest.strip_prefix('.') {
+            let end = r.find(['.', '[']).unwrap_or(r.len());
+            r.split_at(end)
+        } else if let Some(r) = rest.strip_prefix('[') {
+            let end = r
+                .find(']')
+                .ok_or(anyhow!("unclosed [ in JSONPath {}", path))?;
+            let segment = r[..end].trim_matches(|c| c == '\'' || c == '"');
+            (segment, &r[end + 1..])
+        } else {
+            return Err(anyhow!("invalid JSONPath {}", path));
+        };
+        if segment.is_empty() || segment == "*" {
+            return Err(anyhow!("JSONPath {} is not supported", path));
+        }
+        pointer.push('/');
+        pointer.push_str(&segment.replace('~', "~0").replace('/', "~1"));
+        rest = next;
+    }
+    Ok(pointer)
+}
+
+/// Only keep the columns of the query found in `schema`, unknown ones are reported
+/// by the query
+fn projection(schema: Schema, pushdown: &Pushdown) -> Option<Vec<String>> {
+    let columns = pushdown.columns.as_ref().filter(|c| !c.is_empty())?;
+    Some(
+        columns
+            .iter()
+            .filter(|c| schema.contains(c))
+            .cloned()
+            .collect(),
+    )
+}
+
+/// Read arrow ipc data, only the columns referenced by the query are read
+fn read_arrow(data: Bytes, pushdown: &Pushdown) -> Result<DataSet> {
+    // reading the schema moves the reader, the data is read by a fresh one
+    let df = match data.starts_with(b"ARROW1") {
+        true => {
+            let schema = IpcReader::new(Cursor::new(data.clone())).schema()?;
+            IpcReader::new(Cursor::new(data))
+                .with_columns(projection(schema, pushdown))
+                .finish()?
+        }
+        false => {
+            let schema = IpcStreamReader::new(Cursor::new(data.clone())).schema()?;
+            IpcStreamReader::new(Cursor::new(data))
+                .with_columns(projection(schema, pushdown))
+                .finish()?
+        }
+    };
+    Ok(DataSet(df))
+}
+
+/// Read an avro container file, records become rows and nested records struct columns
+fn read_avro(data: Bytes, pushdown: &Pushdown) -> Result<DataSet> {
+    let schema = AvroReader::new(Cursor::new(data.clone())).schema()?;
+    let df = AvroReader::new(Cursor::new(data))
+        .with_columns(projection(schema, pushdown))
+        .finish()?;
+    Ok(DataSet(df))
+}
 
-    fn transform(self) -> Result<DataSet, Self::Error> {
-        let df = JsonReader::new(Cursor::new(self.0))
-            .infer_schema_len(Some(4))
-            .finish()?;
+/// Number of records the schema of a JSON array is inferred from by default
+const JSON_INFER_LEN: usize = 4;
+
+/// Number of lines the schema of newline delimited json is inferred from by default
+const NDJSON_INFER_LEN: usize = 100;
+
+/// Number of rows the schema of csv is inferred from by default
+const CSV_INFER_LEN: usize = 16;
+
+fn read_json(data: Bytes, options: &ReaderOptions) -> Result<DataSet> {
+    let df = JsonReader::new(Cursor::new(data))
+        .infer_schema_len(options.infer_length(JSON_INFER_LEN))
+        .finish()?;
+    Ok(DataSet(df))
+}
+
+/// Integers without leading zeros, codes like `007` stay text
+fn is_integer(text: &str) -> bool {
+    text.parse::<i64>().is_ok_and(|i| i.to_string() == text)
+}
+
+/// Read records converted from another format, the schema is inferred from all of them
+fn read_records<T: serde::Serialize>(records: &[T]) -> Result<DataSet> {
+    let json = serde_json::to_vec(records)?;
+    let df = JsonReader::new(Cursor::new(json))
+        .infer_schema_len(Some(records.len().max(1)))
+        .finish()?;
+    Ok(DataSet(df))
+}
+
+fn read_ndjson(data: Bytes, options: &ReaderOptions) -> Result<DataSet> {
+    let data = json_lines(data, options.ignore_errors)?;
+    let df = JsonLineReader::new(Cursor::new(data))
+        .infer_schema_len(options.infer_length(NDJSON_INFER_LEN))
+        .finish()?;
+    Ok(DataSet(df))
+}
+
+/// Keep the lines holding a JSON object. Blank lines are dropped, malformed
+/// ones too with `ignore_errors`, otherwise they are reported.
+fn json_lines(data: Bytes, ignore_errors: bool) -> Result<Bytes> {
+    let mut lines = BytesMut::with_capacity(data.len());
+    for (i, line) in data.split(|b| *b == b'\n').enumerate() {
+        let line = line.trim_ascii();
+        if line.is_empty() {
+            continue;
+        }
+
+        let valid =
+            line.starts_with(b"{") && serde_json::from_slice::<serde::de::IgnoredAny>(line).is_ok();
+        match valid {
+            true => {
+                lines.extend_from_slice(line);
+                lines.extend_from_slice(b"\n");
+            }
+            false if ignore_errors => {}
+            false => return Err(anyhow!("line {} is not a JSON object", i + 1)),
+        }
+    }
+    Ok(lines.freeze())
+}
+
+fn read_delimited(
+    data: Bytes,
+    file_type: filetype::Filetype,
+    options: &ReaderOptions,
+) -> Result<DataSet> {
+    let delimiter = options.delimiter(&file_type, &data);
+    let dtypes = options.csv_dtypes();
+    let df = options
+        .csv_reader(data, delimiter)
+        .with_skip_rows(options.skip_rows)
+        .infer_schema(options.infer_length(CSV_INFER_LEN))
+        .with_dtypes(dtypes.as_ref())
+        .finish()?;
+    Ok(DataSet(df))
+}
+
+/// Size of the batches of records parsed from a stream
+const BATCH_SIZE: usize = 8 << 20;
+
+/// Parse delimited text arriving in chunks. Complete records are parsed in batches with
+/// the schema inferred from the first one, so the text is never held as a whole unless
+/// the schema is inferred from all records.
+pub(crate) struct DelimitedReader {
+    file_type: filetype::Filetype,
+    options: ReaderOptions,
+    /// given or detected from the first batch
+    delimiter: Option<u8>,
+    batch_size: usize,
+    pending: BytesMut,
+    /// bytes of `pending` already scanned for record boundaries
+    scanned: usize,
+    quoted: bool,
+    /// the last scanned byte is the escape character
+    escaped: bool,
+    /// end of the last complete record in `pending`
+    boundary: Option<usize>,
+    schema: Option<Schema>,
+    df: Option<DataFrame>,
+}
+
+impl DelimitedReader {
+    pub(crate) fn new(file_type: filetype::Filetype, options: ReaderOptions) -> Self {
+        Self {
+            file_type,
+            delimiter: options.delimiter,
+            options,
+            batch_size: BATCH_SIZE,
+            pending: BytesMut::new(),
+            scanned: 0,
+            quoted: false,
+            escaped: false,
+            boundary: None,
+            schema: None,
+            df: None,
+        }
+    }
+
+    pub(crate) fn push(&mut self, chunk: &[u8]) -> Result<()> {
+        let quote = self.options.quote();
+        let escape = self.options.escape_char.filter(|e| *e != quote);
+
+        self.pending.extend_from_slice(chunk);
+        for (i, b) in self.pending[self.scanned..].iter().enumerate() {
+            if self.escaped {
+                self.escaped = false;
+                continue;
+            }
+            match *b {
+                b if Some(b) == escape && self.quoted => self.escaped = true,
+                b if b == quote => self.quoted = !self.quoted,
+                b'\n' if !self.quoted => self.boundary = Some(self.scanned + i),
+                _ => {}
+            }
+        }
+        self.scanned = self.pending.len();
+
+        let Some(end) = self
+            .boundary
+            .filter(|_| self.pending.len() >= self.batch_size)
+        else {
+            return Ok(());
+        };
+        // the schema is inferred from the first batch, it needs the records to infer
+        // it from besides the skipped rows and the header, all of them are only
+        // complete once the stream is
+        let ready = match (&self.schema, self.options.infer_length(CSV_INFER_LEN)) {
+            (Some(_), _) => true,
+            (None, Some(rows)) => {
+                let lines = data_lines(&self.pending[..end], self.options.comment_char);
+                lines > self.options.skip_rows + rows
+            }
+            (None, None) => false,
+        };
+        if ready {
+            let batch = self.pending.split_to(end + 1).freeze();
+            self.scanned -= batch.len();
+            self.boundary = None;
+            self.parse(batch)?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn finish(mut self) -> Result<DataSet> {
+        let rest = self.pending.split().freeze();
+        if self.df.is_none() || !rest.iter().all(u8::is_ascii_whitespace) {
+            self.parse(rest)?;
+        }
+        let mut df = self.df.take().unwrap_or_default();
+        df.rechunk();
+        let df = apply_schema(df, &self.options.schema, &self.options.null_values)?;
         Ok(DataSet(df))
     }
+
+    fn parse(&mut self, batch: Bytes) -> Result<()> {
+        let delimiter = *self
+            .delimiter
+            .get_or_insert_with(|| self.options.delimiter(&self.file_type, &batch));
+        let reader = self.options.csv_reader(batch, delimiter);
+        let dtypes = self.options.csv_dtypes();
+        let df = match &self.schema {
+            Some(schema) => reader.has_header(false).with_schema(schema).finish()?,
+            None => reader
+                .with_skip_rows(self.options.skip_rows)
+                .infer_schema(self.options.infer_length(CSV_INFER_LEN))
+                .with_dtypes(dtypes.as_ref())
+                .finish()?,
+        };
+
+        match &mut self.df {
+            Some(all) => {
+                all.vstack_mut(&df)?;
+            }
+            None => {
+                self.schema = Some(df.schema());
+                self.df = Some(df);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Number of non empty lines, lines starting with `comment` are not counted
+fn data_lines(text: &[u8], comment: Option<u8>) -> usize {
+    text.split(|b| *b == b'\n')
+        .filter(|l| !l.trim_ascii().is_empty())
+        .filter(|l| comment.is_none() || l.first() != comment.as_ref())
+        .count()
+}
+
+/// Read a remote file with range requests, parquet files only fetch their footer
+/// and the column chunks the query needs
+pub(crate) async fn transform_ranges(
+    file_type: filetype::Filetype,
+    mut reader: HttpRangeReader,
+    options: &ReaderOptions,
+    pushdown: &Pushdown<'_>,
+) -> Result<DataSet> {
+    let file_type = options.file_type(file_type);
+    if file_type != filetype::Filetype::Parquet {
+        let data = reader.get(0..reader.size()).await?;
+        return detect_content((file_type, data))?.transform_with(options, pushdown);
+    }
+    let ds = read_remote_parquet(reader, &options.read_pushdown(pushdown)).await?;
+    apply_options(ds, options, pushdown)
+}
+
+/// Parse a csv or tsv download while it arrives, other formats are read once complete
+pub(crate) async fn transform_stream(
+    file_type: filetype::Filetype,
+    mut stream: HttpStream,
+    options: &ReaderOptions,
+) -> Result<DataSet> {
+    let file_type = options.file_type(file_type);
+    if !matches!(file_type, filetype::Filetype::Csv | filetype::Filetype::Tsv) {
+        let data = stream.collect().await?.decode()?;
+        return detect_content((file_type, data))?.transform_with(options, &Pushdown::default());
+    }
+
+    let mut reader = DelimitedReader::new(file_type, options.clone());
+    while let Some(chunk) = stream.chunk().await? {
+        reader.push(&chunk)?;
+    }
+    reader.finish()
 }
 
 #[cfg(test)]
 mod tests {
+    use polars::{io::avro::AvroWriter, prelude::*};
+
     use crate::filetype;
 
-    use super::detect_content;
+    use super::*;
 
     #[test]
     fn detect_content_should_work() {
@@ -79,7 +826,360 @@ mod tests {
         ]
         "#;
 
-        let transform = detect_content((filetype::Filetype::Json, json_data.to_string())).unwrap();
+        let transform = detect_content((filetype::Filetype::Json, json_data.into())).unwrap();
         assert!(transform.transform().is_ok());
     }
+
+    #[test]
+    fn detect_content_should_sniff_unknown() {
+        let data = "a\tb\n1\t2\n3\t4\n";
+        let transform = detect_content((filetype::Filetype::Unknown, data.into())).unwrap();
+        let ds = transform.transform().unwrap();
+        assert_eq!((2, 2), ds.shape());
+
+        let data = "{\"a\": 1}\n{\"a\": 2}\n{\"a\": 3}\n";
+        let transform = detect_content((filetype::Filetype::Unknown, data.into())).unwrap();
+        assert_eq!((3, 1), transform.transform().unwrap().shape());
+
+        assert!(detect_content((filetype::Filetype::Unknown, "plain text".into())).is_err());
+    }
+
+    #[test]
+    fn delimited_reader_should_parse_in_batches() {
+        let data = "id,name\n1,\"a\nb\"\n2,c\n3,d\n4,\"e,f\"\n";
+        let options = ReaderOptions {
+            infer_schema_length: Some(1),
+            ..Default::default()
+        };
+        let mut reader = DelimitedReader::new(filetype::Filetype::Csv, options);
+        reader.batch_size = 8;
+        for chunk in data.as_bytes().chunks(3) {
+            reader.push(chunk).unwrap();
+        }
+        let ds = reader.finish().unwrap();
+
+        assert_eq!((4, 2), ds.shape());
+        assert_eq!(&DataType::Int64, ds.column("id").unwrap().dtype());
+        let names: Vec<_> = ds
+            .column("name")
+            .unwrap()
+            .utf8()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        assert_eq!(vec!["a\nb", "c", "d", "e,f"], names);
+    }
+
+    #[test]
+    fn delimited_reader_should_keep_unquoted_escapes() {
+        // outside of quoted fields the escape is an ordinary character, as when the
+        // text is read at once
+        let data = "id,size\n1,5\\\"\n2,6\"\n3,\"a\\\"b\"\n";
+        let options = || ReaderOptions {
+            escape_char: Some(b'\\'),
+            infer_schema_length: Some(1),
+            ..Default::default()
+        };
+        let mut reader = DelimitedReader::new(filetype::Filetype::Csv, options());
+        reader.batch_size = 4;
+        for chunk in data.as_bytes().chunks(3) {
+            reader.push(chunk).unwrap();
+        }
+        let ds = reader.finish().unwrap();
+        let sizes: Vec<_> = ds
+            .column("size")
+            .unwrap()
+            .utf8()
+            .unwrap()
+            .into_no_null_iter()
+            .collect();
+        assert_eq!(vec!["5\\\"", "6\"", "a\"b"], sizes);
+        let expected = read_delimited(data.into(), filetype::Filetype::Csv, &options()).unwrap();
+        assert!(ds.frame_equal_missing(&expected));
+    }
+
+    #[test]
+    fn delimited_reader_should_infer_from_all_records() {
+        let data = (0..20).fold("id\n".to_string(), |data, i| format!("{}{}\n", data, i));
+        let data = format!("{}x1\n", data);
+        let options = ReaderOptions {
+            infer_schema_length: Some(0),
+            ..Default::default()
+        };
+        let mut reader = DelimitedReader::new(filetype::Filetype::Csv, options);
+        reader.batch_size = 8;
+        for chunk in data.as_bytes().chunks(3) {
+            reader.push(chunk).unwrap();
+        }
+        let ds = reader.finish().unwrap();
+        assert_eq!((21, 1), ds.shape());
+        assert_eq!(&DataType::Utf8, ds.column("id").unwrap().dtype());
+    }
+
+    #[tokio::test]
+    async fn query_with_options_should_apply_reader_options() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("late.csv");
+        let data = (0..20).fold("id\n".to_string(), |data, i| format!("{}{}\n", data, i));
+        std::fs::write(&path, format!("{}x1\n", data)).unwrap();
+
+        let sql = format!("select id from file://{}", path.display());
+        assert!(crate::query(&sql).await.is_err());
+        let options = [("infer_schema_length", "all")];
+        let ds = crate::query_with_options(&sql, &options).await.unwrap();
+        assert_eq!(&DataType::Utf8, ds.column("id").unwrap().dtype());
+
+        // options in the query take precedence
+        let sql = format!("{}(infer_schema_length => 4)", sql);
+        assert!(crate::query_with_options(&sql, &options).await.is_err());
+    }
+
+    #[test]
+    fn json_root_should_select_records() {
+        assert_eq!("/data/items/0", json_pointer("$.data['items'][0]").unwrap());
+        assert_eq!("/a~1b", json_pointer("$['a/b']").unwrap());
+        assert_eq!("/data", json_pointer("/data").unwrap());
+        assert!(json_pointer("$.data[*]").is_err());
+        assert!(json_pointer("data").is_err());
+
+        let data =
+            r#"{"data": {"items": [{"id": 1, "as": "x"}, {"id": 2}]}, "meta": {"total": 2}}"#;
+        for root in ["/data/items", "$.data.items"] {
+            let options = ReaderOptions {
+                json_root: Some(root.into()),
+                ..Default::default()
+            };
+            let ds = detect_content((filetype::Filetype::Json, data.into()))
+                .unwrap()
+                .transform_with(&options, &Pushdown::default())
+                .unwrap();
+            assert_eq!((2, 2), ds.shape());
+            // columns are in the order of the keys
+            assert_eq!(vec!["id", "as"], ds.get_column_names());
+        }
+
+        let options = ReaderOptions::from_args(&SourceArgs::default(), Some("/meta")).unwrap();
+        let ds = detect_content((filetype::Filetype::Json, data.into()))
+            .unwrap()
+            .transform_with(&options, &Pushdown::default())
+            .unwrap();
+        assert_eq!((1, 1), ds.shape());
+
+        let options = ReaderOptions {
+            json_root: Some("/missing".into()),
+            ..Default::default()
+        };
+        assert!(detect_content((filetype::Filetype::Json, data.into()))
+            .unwrap()
+            .transform_with(&options, &Pushdown::default())
+            .is_err());
+    }
+
+    #[test]
+    fn flatten_should_expand_struct_columns() {
+        let data = r#"[{"id": 1, "address": {"city": "x", "geo": {"lat": 1.5}}}]"#;
+        let options = ReaderOptions {
+            flatten: true,
+            ..Default::default()
+        };
+        let ds = detect_content((filetype::Filetype::Json, data.into()))
+            .unwrap()
+            .transform_with(&options, &Pushdown::default())
+            .unwrap();
+        assert_eq!(
+            vec!["id", "address_city", "address_geo_lat"],
+            ds.get_column_names()
+        );
+    }
+
+    #[test]
+    fn ndjson_should_skip_blank_and_malformed_lines() {
+        let data = "{\"a\": 1}\r\n\n{\"a\": 2, \"b\": \"x\"}\n{\"a\": 3\n  \n";
+        let transform = || detect_content((filetype::Filetype::NdJson, data.into())).unwrap();
+        let err = transform().transform().unwrap_err();
+        assert_eq!("line 4 is not a JSON object", err.to_string());
+
+        let args = SourceArgs {
+            named: vec![
+                ("ignore_errors".into(), "true".into()),
+                ("infer_schema_length".into(), "1".into()),
+            ],
+            ..Default::default()
+        };
+        let options = ReaderOptions::from_args(&args, None).unwrap();
+        assert_eq!(Some(1), options.infer_schema_length);
+        let ds = transform()
+            .transform_with(&options, &Pushdown::default())
+            .unwrap();
+        // the schema is inferred from the first record only
+        assert_eq!((2, 1), ds.shape());
+    }
+
+    #[test]
+    fn fragment_arguments_should_apply() {
+        let args = SourceArgs {
+            named: vec![("skip_rows".into(), "2".into())],
+            ..Default::default()
+        };
+        let options = ReaderOptions::from_args(&args, Some("table=div%20%23stats&skip_rows=1"));
+        let options = options.unwrap();
+        assert_eq!(Some("div #stats"), options.table.as_deref());
+        // arguments of the source come last
+        assert_eq!(2, options.skip_rows);
+        assert_eq!(None, options.sheet);
+
+        assert!(ReaderOptions::from_args(&SourceArgs::default(), Some("table=1&x")).is_err());
+    }
+
+    #[test]
+    fn schema_options_should_apply() {
+        // a late outlier of an integer column
+        let mut data = String::from("id,v,day\n");
+        for i in 0..20 {
+            data.push_str(&format!("{},{},NA\n", i, i));
+        }
+        data.push_str("20,1.5,31/01/2023\n");
+        let transform = || detect_content((filetype::Filetype::Csv, data.clone().into())).unwrap();
+        assert!(transform().transform().is_err());
+
+        let read = |named: Vec<(&str, &str)>| {
+            let args = SourceArgs {
+                named: named
+                    .into_iter()
+                    .map(|(n, v)| (n.to_string(), v.to_string()))
+                    .collect(),
+                ..Default::default()
+            };
+            let options = ReaderOptions::from_args(&args, None)?;
+            transform().transform_with(&options, &Pushdown::default())
+        };
+        let ds = read(vec![("infer_schema_length", "all")]).unwrap();
+        assert_eq!(&DataType::Float64, ds.column("v").unwrap().dtype());
+
+        let ds = read(vec![
+            ("schema", "v: float64, day: date(%d/%m/%Y), id: uint32"),
+            ("null_values", "NA"),
+        ])
+        .unwrap();
+        assert_eq!(&DataType::Float64, ds.column("v").unwrap().dtype());
+        assert_eq!(&DataType::UInt32, ds.column("id").unwrap().dtype());
+        assert_eq!(&DataType::Date, ds.column("day").unwrap().dtype());
+        assert_eq!(20, ds.column("day").unwrap().null_count());
+        assert!(read(vec![("schema", "v: int64")]).is_err());
+
+        // a field missing from the first records of json
+        let data = r#"[{"a": 1}, {"a": 2}, {"a": 3}, {"a": 4}, {"a": 5, "b": "x"}]"#;
+        let args = SourceArgs {
+            named: vec![("infer_schema_length".into(), "0".into())],
+            ..Default::default()
+        };
+        let options = ReaderOptions::from_args(&args, None).unwrap();
+        let ds = detect_content((filetype::Filetype::Json, data.into()))
+            .unwrap()
+            .transform_with(&options, &Pushdown::default())
+            .unwrap();
+        assert_eq!((5, 2), ds.shape());
+    }
+
+    #[test]
+    fn arrow_ipc_should_round_trip() {
+        let mut df = df!("id" => [1i64, 2], "name" => ["a", "b"]).unwrap();
+        let mut stream = Vec::new();
+        IpcStreamWriter::new(&mut stream).finish(&mut df).unwrap();
+        let file = DataSet(df).to_arrow_ipc().unwrap();
+
+        for data in [file, stream] {
+            let ds = detect_content((filetype::Filetype::Unknown, data.clone().into()))
+                .unwrap()
+                .transform()
+                .unwrap();
+            assert_eq!((2, 2), ds.shape());
+            assert_eq!(&DataType::Utf8, ds.column("name").unwrap().dtype());
+
+            let pushdown = Pushdown {
+                columns: Some(vec!["id".into(), "missing".into()]),
+                ..Default::default()
+            };
+            let ds = read_arrow(data.into(), &pushdown).unwrap();
+            assert_eq!(vec!["id"], ds.get_column_names());
+        }
+    }
+
+    #[test]
+    fn avro_should_map_nested_and_temporal_types() {
+        let mut df = df!(
+            "id" => [Some(1i64), None],
+            "day" => [19000i32, 19001],
+            "at" => [1_700_000_000_000i64, 1_700_000_001_000],
+        )
+        .unwrap();
+        df.try_apply("day", |s| s.cast(&DataType::Date)).unwrap();
+        df.try_apply("at", |s| {
+            s.cast(&DataType::Datetime(TimeUnit::Milliseconds, None))
+        })
+        .unwrap();
+        let user = StructChunked::new("user", &[Series::new("name", ["a", "b"])]).unwrap();
+        df.with_column(user.into_series()).unwrap();
+
+        let mut data = Vec::new();
+        AvroWriter::new(&mut data).finish(&mut df).unwrap();
+        let ds = detect_content((filetype::Filetype::Unknown, data.into()))
+            .unwrap()
+            .transform()
+            .unwrap();
+
+        assert_eq!((2, 4), ds.shape());
+        assert_eq!(1, ds.column("id").unwrap().null_count());
+        assert_eq!(&DataType::Date, ds.column("day").unwrap().dtype());
+        assert!(matches!(
+            ds.column("at").unwrap().dtype(),
+            DataType::Datetime(TimeUnit::Milliseconds, _)
+        ));
+        assert!(matches!(
+            ds.column("user").unwrap().dtype(),
+            DataType::Struct(_)
+        ));
+    }
+
+    #[test]
+    fn delimited_options_should_apply() {
+        let data = "exported by x\n# note\nid;name\n1;\"a \\\"b\\\"\"\n2;c:\\d\n";
+        let args = SourceArgs {
+            named: vec![
+                ("skip_rows".into(), "1".into()),
+                ("comment".into(), "#".into()),
+                ("escape".into(), "\\".into()),
+            ],
+            ..Default::default()
+        };
+        let options = ReaderOptions::from_args(&args, None).unwrap();
+        assert_eq!(
+            b';',
+            options.delimiter(&filetype::Filetype::Csv, data.as_bytes())
+        );
+
+        let ds = read_delimited(data.into(), filetype::Filetype::Csv, &options).unwrap();
+        assert_eq!((2, 2), ds.shape());
+        let names = ds.column("name").unwrap().utf8().unwrap();
+        assert_eq!(Some("a \"b\""), names.get(0));
+        // outside of quoted fields the escape character is kept
+        assert_eq!(Some("c:\\d"), names.get(1));
+
+        let mut reader = DelimitedReader::new(filetype::Filetype::Csv, options);
+        reader.batch_size = 8;
+        for chunk in data.as_bytes().chunks(3) {
+            reader.push(chunk).unwrap();
+        }
+        assert_eq!((2, 2), reader.finish().unwrap().shape());
+
+        let data = "a|b\tc\n1|2\t3\n";
+        let args = SourceArgs {
+            named: vec![("delimiter".into(), "\\t".into())],
+            ..Default::default()
+        };
+        let options = ReaderOptions::from_args(&args, None).unwrap();
+        assert_eq!(Some(b'\t'), options.delimiter);
+        let ds = read_delimited(data.into(), filetype::Filetype::Csv, &options).unwrap();
+        assert_eq!(vec!["a|b", "c"], ds.get_column_names());
+    }
 }
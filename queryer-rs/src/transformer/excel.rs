@@ -0,0 +1,208 @@
+use std::io::Cursor;
+
+use anyhow::{anyhow, Context, Result};
+use bytes::Bytes;
+use calamine::{open_workbook_auto_from_rs, Data, Reader};
+
+use crate::{
+    column::{to_data_frame, Cell, ColumnBuilder, Kind},
+    DataSet,
+};
+
+use super::ReaderOptions;
+
+/// Read a sheet of a workbook (xlsx, xlsm, xlsb, xls or ods), the first one unless
+/// `options` name one. The first row of the sheet, or of the cell range, holds the
+/// column names.
+pub(crate) fn read_excel(data: Bytes, options: &ReaderOptions) -> Result<DataSet> {
+    let mut workbook = open_workbook_auto_from_rs(Cursor::new(data))?;
+    let sheet = match &options.sheet {
+        Some(sheet) => sheet.clone(),
+        None => workbook
+            .sheet_names()
+            .first()
+            .cloned()
+            .ok_or(anyhow!("workbook has no sheets"))?,
+    };
+    let mut range = workbook
+        .worksheet_range(&sheet)
+        .with_context(|| format!("failed to read sheet {}", sheet))?;
+    if let Some(cells) = &options.range {
+        let (start, end) = cell_range(cells)?;
+        range = range.range(start, end);
+    }
+
+    let mut rows = range.rows().skip(options.skip_rows);
+    let header = rows
+        .next()
+        .ok_or(anyhow!("no header row in sheet {}", sheet))?;
+    let rows: Vec<_> = rows.collect();
+
+    let mut columns = Vec::with_capacity(header.len());
+    for (i, name) in header.iter().enumerate() {
+        let name = match name {
+            Data::Empty => format!("column_{}", i + 1),
+            name => name.to_string(),
+        };
+        let kind = column_kind(rows.iter().map(|row| &row[i]));
+        let mut column = ColumnBuilder::new(&name, kind);
+        for row in &rows {
+            column.push(cell(&row[i], kind));
+        }
+        columns.push(column);
+    }
+    Ok(DataSet(to_data_frame(columns)?))
+}
+
+/// Columns holding dates are `Date` if none has a time of day, other columns
+/// are inferred from their values
+fn column_kind<'a>(cells: impl Iterator<Item = &'a Data>) -> Kind {
+    let mut kind = Kind::Unknown;
+    for datetime in cells.filter_map(|c| match c {
+        Data::DateTime(d) if !d.is_duration() => d.as_datetime(),
+        _ => None,
+    }) {
+        match datetime.date().and_hms_opt(0, 0, 0) == Some(datetime) {
+            true if kind == Kind::Unknown => kind = Kind::Date,
+            true => {}
+            false => return Kind::Datetime,
+        }
+    }
+    kind
+}
+
+fn cell(data: &Data, kind: Kind) -> Cell<'_> {
+    match data {
+        Data::Int(i) => Cell::Int(*i),
+        // excel stores all numbers as floats
+        Data::Float(f) if f.fract() == 0.0 && f.abs() < (1u64 << 53) as f64 => Cell::Int(*f as i64),
+        Data::Float(f) => Cell::Float(*f),
+        Data::String(s) => Cell::Text(s.into()),
+        Data::Bool(b) => Cell::Bool(*b),
+        Data::DateTime(d) => match d.as_datetime().filter(|_| !d.is_duration()) {
+            Some(datetime) if kind == Kind::Date => {
+                Cell::Text(datetime.format("%Y-%m-%d").to_string().into())
+            }
+            Some(datetime) => {
+                Cell::Text(datetime.format("%Y-%m-%d %H:%M:%S%.3f").to_string().into())
+            }
+            None => Cell::Float(d.as_f64()),
+        },
+        Data::DateTimeIso(s) | Data::DurationIso(s) => Cell::Text(s.into()),
+        Data::Error(_) | Data::Empty => Cell::Null,
+    }
+}
+
+/// Zero based (row, column) bounds of a cell range like `B2:D10`
+fn cell_range(range: &str) -> Result<((u32, u32), (u32, u32))> {
+    let (start, end) = range
+        .split_once(':')
+        .ok_or(anyhow!("cell range {} must look like A1:D10", range))?;
+    Ok((cell_position(start)?, cell_position(end)?))
+}
+
+fn cell_position(cell: &str) -> Result<(u32, u32)> {
+    let cell = cell.trim().replace('$', "").to_uppercase();
+    let split = cell
+        .find(|c: char| c.is_ascii_digit())
+        .filter(|i| *i > 0)
+        .ok_or(anyhow!("invalid cell {}", cell))?;
+    let (letters, digits) = cell.split_at(split);
+
+    let mut column = 0u32;
+    for c in letters.chars() {
+        if !c.is_ascii_uppercase() {
+            return Err(anyhow!("invalid cell {}", cell));
+        }
+        column = column * 26 + (c as u32 - 'A' as u32 + 1);
+    }
+    let row: u32 = digits.parse()?;
+    if row == 0 {
+        return Err(anyhow!("invalid cell {}", cell));
+    }
+    Ok((row - 1, column - 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use polars::prelude::*;
+    use rust_xlsxwriter::{ExcelDateTime, Format, Workbook};
+
+    use super::*;
+
+    fn workbook() -> Bytes {
+        let mut workbook = Workbook::new();
+        let date = Format::new().set_num_format("yyyy-mm-dd");
+
+        let sheet = workbook.add_worksheet();
+        sheet.write_string(0, 0, "ignored").unwrap();
+
+        let sheet = workbook.add_worksheet().set_name("Sales").unwrap();
+        sheet.write_string(0, 0, "exported by x").unwrap();
+        for (col, name) in ["day", "units", "price", "paid"].iter().enumerate() {
+            sheet.write_string(1, col as u16, *name).unwrap();
+        }
+        for (i, (units, price)) in [(3.0, 1.5), (4.0, 2.0)].iter().enumerate() {
+            let row = i as u32 + 2;
+            let day = ExcelDateTime::from_ymd(2023, 1, i as u8 + 1).unwrap();
+            sheet
+                .write_datetime_with_format(row, 0, &day, &date)
+                .unwrap();
+            sheet.write_number(row, 1, *units).unwrap();
+            sheet.write_number(row, 2, *price).unwrap();
+            sheet.write_boolean(row, 3, i == 0).unwrap();
+        }
+        workbook.save_to_buffer().unwrap().into()
+    }
+
+    #[tokio::test]
+    async fn query_zip_entry_should_not_name_sheet() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("archive.zip");
+        let mut writer = ::zip::ZipWriter::new(std::fs::File::create(&path).unwrap());
+        writer
+            .start_file("report.xlsx", Default::default())
+            .unwrap();
+        std::io::Write::write_all(&mut writer, &workbook()).unwrap();
+        writer.finish().unwrap();
+
+        let sql = format!("select * from file://{}#report.xlsx", path.display());
+        let ds = crate::query(sql).await.unwrap();
+        assert_eq!(vec!["ignored"], ds.get_column_names());
+    }
+
+    #[test]
+    fn cell_range_should_work() {
+        assert_eq!(((1, 1), (9, 3)), cell_range("B2:D10").unwrap());
+        assert_eq!(((0, 26), (0, 27)), cell_range("$AA$1:ab1").unwrap());
+        assert!(cell_range("B2").is_err());
+        assert!(cell_range("2:D0").is_err());
+    }
+
+    #[test]
+    fn read_excel_should_pick_sheet_and_map_types() {
+        let options = ReaderOptions {
+            sheet: Some("Sales".into()),
+            skip_rows: 1,
+            ..Default::default()
+        };
+        let ds = read_excel(workbook(), &options).unwrap();
+        assert_eq!((2, 4), ds.shape());
+        assert_eq!(&DataType::Date, ds.column("day").unwrap().dtype());
+        assert_eq!(&DataType::Int64, ds.column("units").unwrap().dtype());
+        assert_eq!(&DataType::Float64, ds.column("price").unwrap().dtype());
+        assert_eq!(&DataType::Boolean, ds.column("paid").unwrap().dtype());
+
+        let options = ReaderOptions {
+            sheet: Some("Sales".into()),
+            range: Some("B2:C3".into()),
+            ..Default::default()
+        };
+        let ds = read_excel(workbook(), &options).unwrap();
+        assert_eq!(vec!["units", "price"], ds.get_column_names());
+        assert_eq!(1, ds.height());
+
+        let ds = read_excel(workbook(), &ReaderOptions::default()).unwrap();
+        assert_eq!((0, 1), ds.shape());
+    }
+}
@@ -0,0 +1,211 @@
+use std::io::Cursor;
+
+use anyhow::{anyhow, Context, Result};
+use bytes::Bytes;
+use polars::prelude::*;
+
+use crate::{fetcher::retrieve_data, DataSet};
+
+use super::{push_record, read_delimited, ReaderOptions};
+
+/// A column of fixed width text, `start` is the 0 based offset in characters
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct FixedColumn {
+    pub(crate) name: String,
+    pub(crate) start: usize,
+    pub(crate) width: usize,
+}
+
+/// Parse columns given as `name:width` or `name:start-end` (1 based, inclusive),
+/// e.g. `id:4,name:20` or `id:1-4,name:5-24`. Columns given by width follow each
+/// other, unnamed ones (`4,20`) are called `column_1`, `column_2`, ...
+pub(crate) fn parse_columns(spec: &str) -> Result<Vec<FixedColumn>> {
+    let mut columns = Vec::new();
+    let mut start = 0;
+    for (i, column) in spec.split(',').map(str::trim).enumerate() {
+        let (name, bounds) = match column.rsplit_once(':') {
+            Some((name, bounds)) => (name.trim().to_string(), bounds.trim()),
+            None => (format!("column_{}", i + 1), column),
+        };
+        let column = match bounds.split_once('-') {
+            Some((first, last)) => column_from_range(name, first.parse()?, last.parse()?)?,
+            None => FixedColumn {
+                name,
+                start,
+                width: bounds
+                    .parse()
+                    .with_context(|| format!("invalid fixed width column {}", column))?,
+            },
+        };
+        start = column.start + column.width;
+        columns.push(column);
+    }
+    Ok(columns)
+}
+
+fn column_from_range(name: String, first: usize, last: usize) -> Result<FixedColumn> {
+    if first == 0 || last < first {
+        return Err(anyhow!(
+            "invalid range {}-{} of column {}",
+            first,
+            last,
+            name
+        ));
+    }
+    Ok(FixedColumn {
+        name,
+        start: first - 1,
+        width: last - first + 1,
+    })
+}
+
+/// Read the columns from a layout file: a csv with a `name` and a `width` column,
+/// or `start` and `end` (1 based, inclusive) or `start` and `width` columns
+pub(crate) async fn load_layout(source: &str) -> Result<Vec<FixedColumn>> {
+    let (_, data) = retrieve_data(source)
+        .await
+        .with_context(|| format!("failed to read layout {}", source))?;
+    let df = CsvReader::new(Cursor::new(data)).finish()?;
+
+    let names = df.column("name")?.cast(&DataType::Utf8)?;
+    let numbers = |name: &str| -> Result<Option<Vec<Option<i64>>>> {
+        match df.column(name) {
+            Ok(s) => Ok(Some(s.cast(&DataType::Int64)?.i64()?.into_iter().collect())),
+            Err(_) => Ok(None),
+        }
+    };
+    let (starts, ends, widths) = (numbers("start")?, numbers("end")?, numbers("width")?);
+
+    let mut columns = Vec::with_capacity(df.height());
+    let mut next = 0;
+    for (i, name) in names.utf8()?.into_iter().enumerate() {
+        let name = name.ok_or(anyhow!("layout row {} has no name", i + 1))?;
+        let value = |values: &Option<Vec<Option<i64>>>| {
+            values
+                .as_ref()
+                .and_then(|v| v[i])
+                .map(|v| v.max(0) as usize)
+        };
+        let column = match (value(&starts), value(&ends), value(&widths)) {
+            (Some(start), Some(end), _) => column_from_range(name.to_string(), start, end)?,
+            (Some(start), None, Some(width)) => {
+                column_from_range(name.to_string(), start, (start + width).saturating_sub(1))?
+            }
+            (None, _, Some(width)) => FixedColumn {
+                name: name.to_string(),
+                start: next,
+                width,
+            },
+            _ => {
+                return Err(anyhow!(
+                    "layout row {} has no width or start and end",
+                    i + 1
+                ))
+            }
+        };
+        next = column.start + column.width;
+        columns.push(column);
+    }
+    Ok(columns)
+}
+
+/// Cut the lines of `data` into the columns, values are trimmed and typed like csv
+pub(crate) fn read_fixed_width(data: Bytes, options: &ReaderOptions) -> Result<DataSet> {
+    let columns = options.fixed_width.as_ref().ok_or(anyhow!(
+        "fixed width data needs columns => '...' or layout => '...'"
+    ))?;
+
+    let text = String::from_utf8_lossy(&data);
+    let mut csv = String::with_capacity(data.len() + data.len() / 4);
+    push_record(&mut csv, columns.iter().map(|c| c.name.as_str()));
+
+    let lines = text
+        .lines()
+        .skip(options.skip_rows)
+        .filter(|l| !l.trim().is_empty())
+        .filter(|l| {
+            options
+                .comment_char
+                .is_none_or(|c| !l.starts_with(c as char))
+        });
+    for line in lines {
+        let chars: Vec<_> = line.char_indices().map(|(i, _)| i).collect();
+        let offset = |n: usize| chars.get(n).copied().unwrap_or(line.len());
+        push_record(
+            &mut csv,
+            columns
+                .iter()
+                .map(|c| line[offset(c.start)..offset(c.start + c.width)].trim()),
+        );
+    }
+
+    let options = ReaderOptions {
+        delimiter: Some(b','),
+        ..Default::default()
+    };
+    read_delimited(csv.into(), crate::filetype::Filetype::Csv, &options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_columns_should_work() {
+        let columns = parse_columns("id:4, name:1-3,amount:6").unwrap();
+        assert_eq!(
+            vec![
+                FixedColumn {
+                    name: "id".into(),
+                    start: 0,
+                    width: 4
+                },
+                FixedColumn {
+                    name: "name".into(),
+                    start: 0,
+                    width: 3
+                },
+                FixedColumn {
+                    name: "amount".into(),
+                    start: 3,
+                    width: 6
+                },
+            ],
+            columns
+        );
+        assert_eq!("column_2", parse_columns("2,3").unwrap()[1].name);
+        assert!(parse_columns("id:x").is_err());
+        assert!(parse_columns("id:3-1").is_err());
+    }
+
+    #[tokio::test]
+    async fn read_fixed_width_should_type_columns() {
+        let dir = tempfile::tempdir().unwrap();
+        let layout = dir.path().join("layout.csv");
+        std::fs::write(&layout, "name,start,end\nid,1,4\nname,5,12\namount,13,20\n").unwrap();
+
+        let options = ReaderOptions {
+            fixed_width: Some(
+                load_layout(&format!("file://{}", layout.display()))
+                    .await
+                    .unwrap(),
+            ),
+            skip_rows: 1,
+            ..Default::default()
+        };
+        let data = "HEADER 2023\n0001Zoë \"z\"   12.50\n0002           3.00\n0003bob";
+        let ds = read_fixed_width(data.into(), &options).unwrap();
+
+        assert_eq!((3, 3), ds.shape());
+        assert_eq!(&DataType::Int64, ds.column("id").unwrap().dtype());
+        assert_eq!(&DataType::Float64, ds.column("amount").unwrap().dtype());
+        let names: Vec<_> = ds
+            .column("name")
+            .unwrap()
+            .utf8()
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(vec![Some("Zoë \"z\""), None, Some("bob")], names);
+    }
+}
@@ -0,0 +1,235 @@
+use anyhow::{anyhow, Result};
+use bytes::Bytes;
+use scraper::{ElementRef, Html, Node, Selector};
+
+use crate::{filetype::Filetype, DataSet};
+
+use super::{push_record, read_delimited, ReaderOptions};
+
+/// Cells spanning more columns or rows are cut at this many
+const MAX_SPAN: usize = 1000;
+
+/// Read a table of an HTML page, the first one unless `options.table` gives its
+/// number (1 based, in document order) or a CSS selector. A first row of `<th>`
+/// cells holds the column names, values are typed like csv.
+pub(crate) fn read_html(data: Bytes, options: &ReaderOptions) -> Result<DataSet> {
+    let document = Html::parse_document(&String::from_utf8_lossy(&data));
+    let tables = selector("table")?;
+    let table = match options.table.as_deref().map(|t| (t, t.parse::<usize>())) {
+        None => document.select(&tables).next(),
+        Some((_, Ok(0))) => return Err(anyhow!("tables are numbered from 1")),
+        Some((_, Ok(n))) => document.select(&tables).nth(n - 1),
+        Some((css, Err(_))) => {
+            document
+                .select(&selector(css)?)
+                .next()
+                .and_then(|e| match e.value().name() {
+                    "table" => Some(e),
+                    _ => e.select(&tables).next(),
+                })
+        }
+    }
+    .ok_or(anyhow!(
+        "table {} not found in html",
+        options.table.as_deref().unwrap_or("1")
+    ))?;
+
+    let mut rows = rows(table).into_iter().peekable();
+    let mut header = match rows.peek() {
+        Some(cells) if !cells.is_empty() && cells.iter().all(|(th, _)| *th) => rows
+            .next()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(_, name)| name)
+            .collect(),
+        _ => Vec::new(),
+    };
+    let rows: Vec<Vec<String>> = rows
+        .map(|cells| cells.into_iter().map(|(_, text)| text).collect())
+        .collect();
+
+    let width = rows.iter().map(Vec::len).chain([header.len()]).max();
+    header.resize(width.unwrap_or_default(), String::new());
+    let mut names: Vec<String> = Vec::with_capacity(header.len());
+    for (i, name) in header.into_iter().enumerate() {
+        let name = match name.is_empty() {
+            true => format!("column_{}", i + 1),
+            false => name,
+        };
+        // column names must be unique
+        let name = match names.contains(&name) {
+            true => format!("{}_{}", name, i + 1),
+            false => name,
+        };
+        names.push(name);
+    }
+
+    let mut csv = String::with_capacity(data.len() / 2);
+    push_record(&mut csv, names.iter().map(String::as_str));
+    for row in &rows {
+        let missing = names.len() - row.len();
+        push_record(
+            &mut csv,
+            row.iter()
+                .map(String::as_str)
+                .chain(std::iter::repeat_n("", missing)),
+        );
+    }
+
+    let options = ReaderOptions {
+        delimiter: Some(b','),
+        ..Default::default()
+    };
+    read_delimited(csv.into(), Filetype::Csv, &options)
+}
+
+fn selector(css: &str) -> Result<Selector> {
+    Selector::parse(css).map_err(|e| anyhow!("invalid css selector {}: {}", css, e))
+}
+
+/// The rows of `table` without those of nested tables, a cell is `(is <th>, text)`
+/// repeated for each column and row it spans
+fn rows(table: ElementRef) -> Vec<Vec<(bool, String)>> {
+    let sections = table.children().filter_map(ElementRef::wrap);
+    let rows = sections.flat_map(|e| match e.value().name() {
+        "thead" | "tbody" | "tfoot" => e.children().filter_map(ElementRef::wrap).collect(),
+        _ => vec![e],
+    });
+
+    // cells of earlier rows spanning down, with the number of rows left, by column
+    let mut spans: Vec<(usize, (bool, String))> = Vec::new();
+    rows.filter(|row| row.value().name() == "tr")
+        .map(|row| {
+            let mut cells = Vec::new();
+            for cell in row.children().filter_map(ElementRef::wrap) {
+                let th = match cell.value().name() {
+                    "th" => true,
+                    "td" => false,
+                    _ => continue,
+                };
+                let text = text(cell);
+                let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+                let rows = span(cell, "rowspan");
+                fill_spans(&mut cells, &mut spans);
+                for _ in 0..span(cell, "colspan") {
+                    if rows > 1 {
+                        if spans.len() <= cells.len() {
+                            spans.resize(cells.len() + 1, Default::default());
+                        }
+                        spans[cells.len()] = (rows - 1, (th, text.clone()));
+                    }
+                    cells.push((th, text.clone()));
+                }
+            }
+            fill_spans(&mut cells, &mut spans);
+            cells
+        })
+        .filter(|cells| !cells.is_empty())
+        .collect()
+}
+
+/// Append the cells of earlier rows which span down into the next columns
+fn fill_spans(cells: &mut Vec<(bool, String)>, spans: &mut [(usize, (bool, String))]) {
+    while let Some((rows, cell)) = spans.get_mut(cells.len()).filter(|(rows, _)| *rows > 0) {
+        *rows -= 1;
+        cells.push(cell.clone());
+    }
+}
+
+fn span(cell: ElementRef, attr: &str) -> usize {
+    cell.value()
+        .attr(attr)
+        .and_then(|s| s.trim().parse::<usize>().ok())
+        .unwrap_or(1)
+        .clamp(1, MAX_SPAN)
+}
+
+/// The text of `element` without that of the tables nested in it
+fn text(element: ElementRef) -> String {
+    let mut text = String::new();
+    for node in element.children() {
+        match (ElementRef::wrap(node), node.value()) {
+            (Some(child), _) if child.value().name() != "table" => {
+                text.push_str(&self::text(child))
+            }
+            (None, Node::Text(t)) => text.push_str(t),
+            _ => {}
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use polars::prelude::*;
+
+    use super::*;
+
+    const PAGE: &str = r#"<!DOCTYPE html>
+        <html><body>
+          <table id="nav"><tr><td>home</td></tr></table>
+          <div class="stats">
+            <table>
+              <thead><tr><th>Country</th><th>Population</th><th colspan="2">Area</th></tr></thead>
+              <tbody>
+                <tr><th>Iceland</th><td>372 520</td><td>103000</td><td>km²</td></tr>
+                <tr><th>Malta</th><td>535064</td><td>316</td><td>km²</td></tr>
+                <tr><td>Nested</td><td><table><tr><td>x</td></tr></table></td></tr>
+              </tbody>
+            </table>
+          </div>
+        </body></html>"#;
+
+    fn read(table: Option<&str>) -> Result<DataSet> {
+        let options = ReaderOptions {
+            table: table.map(String::from),
+            ..Default::default()
+        };
+        read_html(PAGE.into(), &options)
+    }
+
+    #[test]
+    fn read_html_should_select_table() {
+        let ds = read(None).unwrap();
+        assert_eq!((1, 1), ds.shape());
+        assert_eq!(vec!["column_1"], ds.get_column_names());
+
+        let ds = read(Some("2")).unwrap();
+        assert_eq!(
+            vec!["Country", "Population", "Area", "Area_4"],
+            ds.get_column_names()
+        );
+        assert_eq!(3, ds.height());
+        assert_eq!(&DataType::Int64, ds.column("Area").unwrap().dtype());
+        assert_eq!(&DataType::Utf8, ds.column("Population").unwrap().dtype());
+        assert_eq!(1, ds.column("Area_4").unwrap().null_count());
+        // the text of the nested table is not part of the cell
+        assert_eq!(1, ds.column("Population").unwrap().null_count());
+
+        assert_eq!(3, read(Some("div.stats")).unwrap().height());
+        assert_eq!(1, read(Some("#nav")).unwrap().height());
+        assert!(read(Some("4")).is_err());
+        assert!(read(Some("0")).is_err());
+        assert!(read(Some("#missing")).is_err());
+    }
+
+    #[test]
+    fn read_html_should_repeat_spanning_cells() {
+        let page = r#"<table>
+            <tr><th>Region</th><th>City</th><th>Rank</th></tr>
+            <tr><td rowspan="2">North</td><td>Oslo</td><td>1</td></tr>
+            <tr><td>Bergen</td><td>2</td></tr>
+            <tr><td colspan="2" rowspan="2">n/a</td><td>3</td></tr>
+            <tr><td>4</td></tr>
+        </table>"#;
+        let ds = read_html(page.into(), &ReaderOptions::default()).unwrap();
+        assert_eq!((4, 3), ds.shape());
+        let column = |name| -> Vec<_> {
+            let s = ds.column(name).unwrap().utf8().unwrap();
+            s.into_iter().map(|v| v.unwrap().to_string()).collect()
+        };
+        assert_eq!(vec!["North", "North", "n/a", "n/a"], column("Region"));
+        assert_eq!(vec!["Oslo", "Bergen", "n/a", "n/a"], column("City"));
+        assert_eq!(&DataType::Int64, ds.column("Rank").unwrap().dtype());
+    }
+}
@@ -0,0 +1,266 @@
+use anyhow::{anyhow, Context, Result};
+use bytes::Bytes;
+use chrono::DateTime;
+use regex::Regex;
+
+use crate::{
+    column::{to_data_frame, Cell, ColumnBuilder, Kind},
+    DataSet,
+};
+
+use super::{is_integer, ReaderOptions};
+
+/// `host ident user [time] "request" status bytes`
+const COMMON: &str = r#"^(?P<host>\S+) (?P<ident>\S+) (?P<user>\S+) \[(?P<timestamp>[^\]]+)\] "(?:(?P<method>\S+) (?P<path>\S+)(?: (?P<protocol>[^"]+))?|[^"]*)" (?P<status>\d{3}) (?P<bytes>\S+)"#;
+
+/// Appended to the common log format, makes the combined log format
+const REFERER_USER_AGENT: &str = r#" "(?P<referer>[^"]*)" "(?P<user_agent>[^"]*)""#;
+
+/// Time of the common log format, e.g. `10/Oct/2000:13:55:36 -0700`
+const CLF_TIME: &str = "%d/%b/%Y:%H:%M:%S %z";
+
+/// Read log lines with `options.log_format`: `common`, `combined` or a regex whose
+/// named groups are the columns. Without a format the first line decides between
+/// combined and common. `-` is a missing value.
+pub(crate) fn read_log(data: Bytes, options: &ReaderOptions) -> Result<DataSet> {
+    let text = String::from_utf8_lossy(&data);
+    let mut lines = text
+        .lines()
+        .enumerate()
+        .filter(|(_, l)| !l.trim().is_empty())
+        .peekable();
+
+    let combined = format!("{}{}", COMMON, REFERER_USER_AGENT);
+    let pattern = match options.log_format.as_deref() {
+        Some(f) if f.eq_ignore_ascii_case("common") => COMMON,
+        Some(f) if f.eq_ignore_ascii_case("combined") => &combined,
+        Some(pattern) => pattern,
+        None => match lines.peek() {
+            Some((_, line)) if Regex::new(&combined)?.is_match(line) => &combined,
+            _ => COMMON,
+        },
+    };
+    let regex = Regex::new(pattern).with_context(|| format!("invalid log format {}", pattern))?;
+    let names: Vec<_> = regex.capture_names().flatten().collect();
+    if names.is_empty() {
+        return Err(anyhow!("log format {} has no named groups", pattern));
+    }
+
+    let mut values: Vec<Vec<Option<&str>>> = vec![Vec::new(); names.len()];
+    for (i, line) in lines {
+        let Some(captures) = regex.captures(line) else {
+            match options.ignore_errors {
+                true => continue,
+                false => return Err(anyhow!("line {} does not match the log format", i + 1)),
+            }
+        };
+        for (name, values) in names.iter().zip(values.iter_mut()) {
+            let value = captures.name(name).map(|m| m.as_str());
+            values.push(value.filter(|v| !v.is_empty() && *v != "-"));
+        }
+    }
+
+    let mut columns = Vec::with_capacity(names.len());
+    for (name, values) in names.iter().zip(values) {
+        let timestamps: Option<Vec<_>> = values
+            .iter()
+            .map(|v| match v {
+                Some(v) => timestamp(v).map(Some),
+                None => Some(None),
+            })
+            .collect();
+        let column = match timestamps {
+            Some(timestamps) if values.iter().any(Option::is_some) => {
+                let mut column = ColumnBuilder::new(name, Kind::Datetime);
+                for t in timestamps {
+                    column.push(t.map_or(Cell::Null, |t| Cell::Text(t.into())));
+                }
+                column
+            }
+            _ => {
+                let kind = kind(&values);
+                let mut column = ColumnBuilder::new(name, kind);
+                for v in values {
+                    column.push(match (v, kind) {
+                        (Some(v), Kind::Int) => v.parse().map_or(Cell::Null, Cell::Int),
+                        (Some(v), Kind::Float) => v.parse().map_or(Cell::Null, Cell::Float),
+                        (Some(v), _) => Cell::Text(v.into()),
+                        (None, _) => Cell::Null,
+                    });
+                }
+                column
+            }
+        };
+        columns.push(column);
+    }
+    Ok(DataSet(to_data_frame(columns)?))
+}
+
+/// A time of the common log format or RFC 3339 in UTC
+fn timestamp(text: &str) -> Option<String> {
+    DateTime::parse_from_str(text, CLF_TIME)
+        .or_else(|_| DateTime::parse_from_rfc3339(text))
+        .ok()
+        .map(|t| t.naive_utc().format("%Y-%m-%d %H:%M:%S%.3f").to_string())
+}
+
+/// The type of a column from all its values: integers, numbers or else text
+fn kind(values: &[Option<&str>]) -> Kind {
+    let present = || values.iter().flatten();
+    if present().next().is_none() {
+        Kind::Unknown
+    } else if present().all(|v| is_integer(v)) {
+        Kind::Int
+    } else if present().all(|v| v.parse::<f64>().is_ok()) {
+        Kind::Float
+    } else {
+        Kind::Text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use polars::prelude::*;
+
+    use super::*;
+
+    const ACCESS_LOG: &str = r#"127.0.0.1 - frank [10/Oct/2000:13:55:36 -0700] "GET /apache_pb.gif HTTP/1.0" 200 2326 "http://www.example.com/start.html" "Mozilla/4.08"
+10.0.0.2 - - [10/Oct/2000:13:56:01 -0700] "POST /login HTTP/1.1" 302 - "-" "curl/7.68.0"
+
+10.0.0.3 - - [10/Oct/2000:13:57:12 +0000] "-" 408 0 "-" "-"
+"#;
+
+    #[test]
+    fn read_log_should_detect_combined_format() {
+        let ds = read_log(ACCESS_LOG.into(), &ReaderOptions::default()).unwrap();
+        assert_eq!((3, 11), ds.shape());
+        assert_eq!(
+            &DataType::Datetime(TimeUnit::Milliseconds, None),
+            ds.column("timestamp").unwrap().dtype()
+        );
+        assert_eq!(&DataType::Int64, ds.column("status").unwrap().dtype());
+        assert_eq!(&DataType::Int64, ds.column("bytes").unwrap().dtype());
+        assert_eq!(1, ds.column("bytes").unwrap().null_count());
+        assert_eq!(1, ds.column("method").unwrap().null_count());
+
+        let options = ReaderOptions {
+            log_format: Some("common".into()),
+            ..Default::default()
+        };
+        assert_eq!(
+            (3, 9),
+            read_log(ACCESS_LOG.into(), &options).unwrap().shape()
+        );
+    }
+
+    #[tokio::test]
+    async fn query_log_should_group_by() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("access.log");
+        std::fs::write(&path, format!("{}{}", ACCESS_LOG, ACCESS_LOG)).unwrap();
+
+        let sql = format!(
+            "SELECT status, count(*) AS hits, sum(bytes) FROM file://{} \
+            WHERE status < 400 GROUP BY status ORDER BY hits DESC",
+            path.display()
+        );
+        let ds = crate::query(sql).await.unwrap();
+        assert_eq!(vec!["status", "hits", "sum"], ds.get_column_names());
+        let hits: Vec<_> = ds
+            .column("hits")
+            .unwrap()
+            .u32()
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(vec![Some(2), Some(2)], hits);
+        let bytes: Vec<_> = ds
+            .column("sum")
+            .unwrap()
+            .i64()
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(vec![Some(4652), None], bytes);
+    }
+
+    #[tokio::test]
+    async fn query_log_should_filter_groups_with_having() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("access.log");
+        let first = ACCESS_LOG.lines().next().unwrap();
+        std::fs::write(&path, format!("{}{}\n", ACCESS_LOG, first)).unwrap();
+
+        let statuses = |ds: DataSet| -> Vec<_> {
+            let status = ds.column("status").unwrap().i64().unwrap();
+            status.into_iter().collect()
+        };
+        let sql = format!(
+            "SELECT status FROM file://{} GROUP BY status HAVING count(*) > 1",
+            path.display()
+        );
+        assert_eq!(vec![Some(200)], statuses(crate::query(sql).await.unwrap()));
+
+        // aliases of the selection can be used as well
+        let sql = format!(
+            "SELECT status, count(*) AS hits FROM file://{} GROUP BY status HAVING hits < 2",
+            path.display()
+        );
+        assert_eq!(
+            vec![Some(302), Some(408)],
+            statuses(crate::query(sql).await.unwrap())
+        );
+
+        let sql = format!(
+            "SELECT status FROM file://{} HAVING count(*) > 1",
+            path.display()
+        );
+        assert!(crate::query(sql).await.is_err());
+    }
+
+    #[test]
+    fn read_log_should_use_named_groups() {
+        let data = "2023-01-02T03:04:05Z WARN disk 91.5\n2023-01-02T03:04:06Z INFO ok\n";
+        let options = ReaderOptions {
+            log_format: Some(
+                r"^(?P<time>\S+) (?P<level>\w+) (?P<message>\w+)(?: (?P<usage>\S+))?$".into(),
+            ),
+            ..Default::default()
+        };
+        let ds = read_log(data.into(), &options).unwrap();
+        assert_eq!(
+            vec!["time", "level", "message", "usage"],
+            ds.get_column_names()
+        );
+        assert!(matches!(
+            ds.column("time").unwrap().dtype(),
+            DataType::Datetime(_, _)
+        ));
+        assert_eq!(&DataType::Float64, ds.column("usage").unwrap().dtype());
+
+        let data = format!("{}\ngarbage\n", data);
+        let err = read_log(data.clone().into(), &options).unwrap_err();
+        assert_eq!("line 4 does not match the log format", err.to_string());
+        let options = ReaderOptions {
+            ignore_errors: true,
+            ..options
+        };
+        assert_eq!(2, read_log(data.into(), &options).unwrap().height());
+
+        // a column is typed from all of its values
+        let data = "a 12\nb 007\nc x1\n";
+        let options = ReaderOptions {
+            log_format: Some(r"^(?P<name>\w) (?P<code>\w+)$".into()),
+            ..Default::default()
+        };
+        let ds = read_log(data.into(), &options).unwrap();
+        let codes: Vec<_> = ds
+            .column("code")
+            .unwrap()
+            .utf8()
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(vec![Some("12"), Some("007"), Some("x1")], codes);
+    }
+}
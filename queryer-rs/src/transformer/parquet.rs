@@ -0,0 +1,419 @@
+use std::{cmp::Ordering, io::Cursor, ops::Range, sync::Arc};
+
+use anyhow::Result;
+use arrow_array::{RecordBatch, RecordBatchReader};
+use arrow_ipc::writer::StreamWriter;
+use arrow_schema::Schema;
+use bytes::Bytes;
+use futures::{future::BoxFuture, FutureExt, TryStreamExt};
+use parquet::{
+    arrow::{
+        arrow_reader::{ArrowReaderBuilder, ParquetRecordBatchReaderBuilder},
+        async_reader::AsyncFileReader,
+        ParquetRecordBatchStreamBuilder, ProjectionMask,
+    },
+    errors::ParquetError,
+    file::{
+        metadata::{ParquetMetaData, ParquetMetaDataReader, RowGroupMetaData},
+        statistics::Statistics,
+    },
+};
+use polars::prelude::*;
+use sqlparser::ast::{BinaryOperator, Expr as SqlExpr, UnaryOperator, Value as SqlValue};
+
+use crate::{ast_convert::Pushdown, fetcher::HttpRangeReader, DataSet};
+
+/// Bytes fetched from the end of a remote file, usually enough for the whole footer
+const FOOTER_PREFETCH: usize = 64 << 10;
+
+/// Read a Parquet file, decoding only the columns referenced by the query and the
+/// row groups whose statistics don't rule out the condition
+pub(crate) fn read_parquet(data: Bytes, pushdown: &Pushdown) -> Result<DataSet> {
+    let builder = ParquetRecordBatchReaderBuilder::try_new(data)?;
+    let reader = with_pushdown(builder, pushdown).build()?;
+    let schema = reader.schema();
+    let batches = reader.collect::<Result<Vec<_>, _>>()?;
+    to_data_set(&schema, batches)
+}
+
+/// Like [`read_parquet`], fetching only the footer and the column chunks to decode
+pub(crate) async fn read_remote_parquet(
+    reader: HttpRangeReader,
+    pushdown: &Pushdown<'_>,
+) -> Result<DataSet> {
+    let builder = ParquetRecordBatchStreamBuilder::new(RemoteFile(reader)).await?;
+    let stream = with_pushdown(builder, pushdown).build()?;
+    let schema = stream.schema().clone();
+    let batches = stream.try_collect().await?;
+    to_data_set(&schema, batches)
+}
+
+fn with_pushdown<T>(
+    mut builder: ArrowReaderBuilder<T>,
+    pushdown: &Pushdown,
+) -> ArrowReaderBuilder<T> {
+    if let Some(columns) = pushdown.columns.as_ref().filter(|c| !c.is_empty()) {
+        let schema = builder.parquet_schema();
+        let roots = schema
+            .root_schema()
+            .get_fields()
+            .iter()
+            .enumerate()
+            .filter(|(_, field)| columns.iter().any(|c| c == field.name()))
+            .map(|(i, _)| i);
+        let projection = ProjectionMask::roots(schema, roots);
+        builder = builder.with_projection(projection);
+    }
+
+    match pushdown.condition {
+        Some(condition) => {
+            let metadata = builder.metadata().clone();
+            let row_groups = metadata
+                .row_groups()
+                .iter()
+                .enumerate()
+                .filter(|(_, rg)| !excluded(condition, rg))
+                .map(|(i, _)| i)
+                .collect();
+            builder.with_row_groups(row_groups)
+        }
+        None => match pushdown.limit {
+            Some(limit) => builder.with_limit(limit),
+            None => builder,
+        },
+    }
+}
+
+/// Hand the record batches over to polars in the arrow ipc stream format
+fn to_data_set(schema: &Schema, batches: Vec<RecordBatch>) -> Result<DataSet> {
+    let mut buf = Vec::new();
+    let mut writer = StreamWriter::try_new(&mut buf, schema)?;
+    for batch in &batches {
+        writer.write(batch)?;
+    }
+    writer.finish()?;
+    drop(writer);
+
+    let df = IpcStreamReader::new(Cursor::new(buf)).finish()?;
+    Ok(DataSet(df))
+}
+
+struct RemoteFile(HttpRangeReader);
+
+impl AsyncFileReader for RemoteFile {
+    fn get_bytes(&mut self, range: Range<usize>) -> BoxFuture<'_, parquet::errors::Result<Bytes>> {
+        async move {
+            self.0
+                .get(range.start as u64..range.end as u64)
+                .await
+                .map_err(|e| ParquetError::External(e.into()))
+        }
+        .boxed()
+    }
+
+    fn get_metadata(&mut self) -> BoxFuture<'_, parquet::errors::Result<Arc<ParquetMetaData>>> {
+        async move {
+            let size = self.0.size() as usize;
+            let metadata = ParquetMetaDataReader::new()
+                .with_prefetch_hint(Some(FOOTER_PREFETCH))
+                .load_and_finish(self, size)
+                .await?;
+            Ok(Arc::new(metadata))
+        }
+        .boxed()
+    }
+}
+
+/// A bound of the values of a column in a row group, or a literal compared to them
+#[derive(Debug, PartialEq)]
+enum Bound<'a> {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Text(&'a str),
+}
+
+impl<'a> PartialOrd for Bound<'a> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (self, other) {
+            (Bound::Int(a), Bound::Int(b)) => a.partial_cmp(b),
+            (Bound::Int(a), Bound::Float(b)) => (*a as f64).partial_cmp(b),
+            (Bound::Float(a), Bound::Int(b)) => a.partial_cmp(&(*b as f64)),
+            (Bound::Float(a), Bound::Float(b)) => a.partial_cmp(b),
+            (Bound::Bool(a), Bound::Bool(b)) => a.partial_cmp(b),
+            (Bound::Text(a), Bound::Text(b)) => a.partial_cmp(b),
+            _ => None,
+        }
+    }
+}
+
+/// `true` if the statistics of the row group show that no row satisfies `expr`.
+/// Rows with nulls never satisfy a comparison, so null counts don't matter.
+fn excluded(expr: &SqlExpr, rg: &RowGroupMetaData) -> bool {
+    match expr {
+        SqlExpr::Nested(expr) => excluded(expr, rg),
+        SqlExpr::BinaryOp {
+            left,
+            op: BinaryOperator::And,
+            right,
+        } => excluded(left, rg) || excluded(right, rg),
+        SqlExpr::BinaryOp {
+            left,
+            op: BinaryOperator::Or,
+            right,
+        } => excluded(left, rg) && excluded(right, rg),
+        SqlExpr::BinaryOp { left, op, right } => {
+            let (name, op, value) = match (left.as_ref(), right.as_ref()) {
+                (SqlExpr::Identifier(id), value) => (&id.value, op.clone(), value),
+                (value, SqlExpr::Identifier(id)) => (&id.value, flip(op), value),
+                _ => return false,
+            };
+            match (column_range(rg, name), literal(value)) {
+                (Some((min, max)), Some(v)) => match op {
+                    BinaryOperator::Eq => v < min || v > max,
+                    BinaryOperator::NotEq => min == max && v == min,
+                    BinaryOperator::Gt => max <= v,
+                    BinaryOperator::GtEq => max < v,
+                    BinaryOperator::Lt => min >= v,
+                    BinaryOperator::LtEq => min > v,
+                    _ => false,
+                },
+                _ => false,
+            }
+        }
+        _ => false,
+    }
+}
+
+/// The operator of `value op column` as seen from the column
+fn flip(op: &BinaryOperator) -> BinaryOperator {
+    match op {
+        BinaryOperator::Gt => BinaryOperator::Lt,
+        BinaryOperator::GtEq => BinaryOperator::LtEq,
+        BinaryOperator::Lt => BinaryOperator::Gt,
+        BinaryOperator::LtEq => BinaryOperator::GtEq,
+        op => op.clone(),
+    }
+}
+
+fn literal(expr: &SqlExpr) -> Option<Bound<'_>> {
+    match expr {
+        SqlExpr::Value(SqlValue::Number(v, _)) => v
+            .parse()
+            .map(Bound::Int)
+            .or_else(|_| v.parse().map(Bound::Float))
+            .ok(),
+        SqlExpr::Value(SqlValue::SingleQuotedString(v)) => Some(Bound::Text(v)),
+        SqlExpr::Value(SqlValue::Boolean(v)) => Some(Bound::Bool(*v)),
+        SqlExpr::UnaryOp {
+            op: UnaryOperator::Minus,
+            expr,
+        } => match literal(expr)? {
+            Bound::Int(v) => Some(Bound::Int(-v)),
+            Bound::Float(v) => Some(Bound::Float(-v)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Min and max of a top level column of the row group, if they were recorded
+fn column_range<'a>(rg: &'a RowGroupMetaData, name: &str) -> Option<(Bound<'a>, Bound<'a>)> {
+    let column = rg
+        .columns()
+        .iter()
+        .find(|c| c.column_descr().path().parts() == [name])?;
+
+    fn range<'a, T>(
+        min: Option<&'a T>,
+        max: Option<&'a T>,
+        f: impl Fn(&'a T) -> Option<Bound<'a>>,
+    ) -> Option<(Bound<'a>, Bound<'a>)> {
+        Some((f(min?)?, f(max?)?))
+    }
+
+    match column.statistics()? {
+        Statistics::Boolean(s) => range(s.min_opt(), s.max_opt(), |v| Some(Bound::Bool(*v))),
+        Statistics::Int32(s) => range(s.min_opt(), s.max_opt(), |v| Some(Bound::Int(*v as i64))),
+        Statistics::Int64(s) => range(s.min_opt(), s.max_opt(), |v| Some(Bound::Int(*v))),
+        Statistics::Float(s) => range(s.min_opt(), s.max_opt(), |v| Some(Bound::Float(*v as f64))),
+        Statistics::Double(s) => range(s.min_opt(), s.max_opt(), |v| Some(Bound::Float(*v))),
+        Statistics::ByteArray(s) => range(s.min_opt(), s.max_opt(), |v| {
+            v.as_utf8().ok().map(Bound::Text)
+        }),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+
+    use arrow_array::{ArrayRef, Int64Array, StringArray, StructArray};
+    use parquet::{arrow::ArrowWriter, file::properties::WriterProperties};
+    use sqlparser::parser::Parser;
+    use wiremock::{matchers::path, Mock, MockServer, Respond, ResponseTemplate};
+
+    use crate::{ast_convert::Sql, dialect::SqlDialect};
+
+    use super::*;
+
+    /// 3 row groups: ids 1..=2, 3..=4 and 5..=6
+    fn parquet_file() -> Bytes {
+        let ids: ArrayRef = Arc::new(Int64Array::from_iter_values(1..=6));
+        let names: ArrayRef = Arc::new(StringArray::from_iter_values([
+            "a", "b", "c", "d", "e", "f",
+        ]));
+        let batch = RecordBatch::try_from_iter([("id", ids), ("name", names)]).unwrap();
+
+        let props = WriterProperties::builder()
+            .set_max_row_group_size(2)
+            .build();
+        let mut buf = Vec::new();
+        let mut writer = ArrowWriter::try_new(&mut buf, batch.schema(), Some(props)).unwrap();
+        writer.write(&batch).unwrap();
+        writer.close().unwrap();
+        buf.into()
+    }
+
+    fn read(sql: &str) -> DataSet {
+        let statement = &Parser::parse_sql(&SqlDialect, sql).unwrap()[0];
+        let sql: Sql = statement.try_into().unwrap();
+        read_parquet(parquet_file(), &sql.pushdown).unwrap()
+    }
+
+    #[test]
+    fn read_parquet_should_work() {
+        let ds = read("select * from file://a.parquet");
+        assert_eq!((6, 2), ds.shape());
+        assert_eq!(&DataType::Int64, ds.column("id").unwrap().dtype());
+        assert_eq!(&DataType::Utf8, ds.column("name").unwrap().dtype());
+    }
+
+    #[test]
+    fn read_parquet_should_push_down() {
+        let ds = read("select name from file://a.parquet");
+        assert_eq!(vec!["name"], ds.get_column_names());
+
+        // only the row groups which may hold matching rows are decoded
+        let ds = read("select id from file://a.parquet where id >= 4 and name <> 'x'");
+        assert_eq!((4, 2), ds.shape());
+        let ds = read("select id from file://a.parquet where 2 > id or name = 'f'");
+        assert_eq!((4, 2), ds.shape());
+        let ds = read("select id from file://a.parquet where id = 7");
+        assert_eq!(0, ds.height());
+
+        let ds = read("select id from file://a.parquet limit 3");
+        assert_eq!((3, 1), ds.shape());
+    }
+
+    #[tokio::test]
+    async fn query_parquet_should_project_flattened_columns() {
+        let city: ArrayRef = Arc::new(StringArray::from_iter_values(["x", "y"]));
+        let address: ArrayRef = Arc::new(StructArray::try_from(vec![("city", city)]).unwrap());
+        let ids: ArrayRef = Arc::new(Int64Array::from_iter_values(1..=2));
+        let batch = RecordBatch::try_from_iter([("id", ids), ("address", address)]).unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("users.parquet");
+        let file = std::fs::File::create(&path).unwrap();
+        let mut writer = ArrowWriter::try_new(file, batch.schema(), None).unwrap();
+        writer.write(&batch).unwrap();
+        writer.close().unwrap();
+
+        let sql = format!(
+            "select address_city from file://{}(flatten => 'true') where id = 2",
+            path.display()
+        );
+        let ds = crate::query(sql).await.unwrap();
+        let cities: Vec<_> = ds
+            .column("address_city")
+            .unwrap()
+            .utf8()
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(vec![Some("y")], cities);
+    }
+
+    /// Serves a file, answering range requests if `ranges` is set, and counts the bytes sent
+    struct RangeFile {
+        data: Bytes,
+        ranges: bool,
+        sent: Arc<AtomicUsize>,
+    }
+
+    impl Respond for RangeFile {
+        fn respond(&self, req: &wiremock::Request) -> ResponseTemplate {
+            let range = req
+                .headers
+                .get(&"range".into())
+                .and_then(|v| v.last().as_str().strip_prefix("bytes="))
+                .and_then(|r| r.split_once('-'))
+                .and_then(|(a, b)| Some((a.parse::<usize>().ok()?, b.parse::<usize>().ok()?)))
+                .filter(|_| self.ranges);
+            let (status, start, end) = match range {
+                Some((start, end)) => (206, start, end.min(self.data.len() - 1)),
+                None => (200, 0, self.data.len() - 1),
+            };
+            self.sent
+                .fetch_add(end + 1 - start, AtomicOrdering::Relaxed);
+            let content_range = format!("bytes {}-{}/{}", start, end, self.data.len());
+            ResponseTemplate::new(status)
+                .insert_header("Content-Range", content_range.as_str())
+                .set_body_bytes(self.data.slice(start..=end).to_vec())
+        }
+    }
+
+    #[tokio::test]
+    async fn query_remote_parquet_should_read_ranges() {
+        // 3 row groups of 50000 rows
+        let ids: ArrayRef = Arc::new(Int64Array::from_iter_values(1..=150_000));
+        let names: ArrayRef = Arc::new(StringArray::from_iter_values(
+            (1..=150_000).map(|i| format!("name {}", i)),
+        ));
+        let batch = RecordBatch::try_from_iter([("id", ids), ("name", names)]).unwrap();
+        let props = WriterProperties::builder()
+            .set_max_row_group_size(50_000)
+            .build();
+        let mut buf = Vec::new();
+        let mut writer = ArrowWriter::try_new(&mut buf, batch.schema(), Some(props)).unwrap();
+        writer.write(&batch).unwrap();
+        writer.close().unwrap();
+        let data = Bytes::from(buf);
+
+        let server = MockServer::start().await;
+        let sent = Arc::new(AtomicUsize::new(0));
+        for (name, ranges) in [("/ranges.parquet", true), ("/plain.parquet", false)] {
+            Mock::given(path(name))
+                .respond_with(RangeFile {
+                    data: data.clone(),
+                    ranges,
+                    sent: sent.clone(),
+                })
+                .mount(&server)
+                .await;
+        }
+
+        let sql = format!(
+            "select name from {}/ranges.parquet where id > 140000",
+            server.uri()
+        );
+        assert_eq!((10_000, 1), crate::query(sql).await.unwrap().shape());
+        // the footer and the chunks of the last row group
+        let read = sent.swap(0, AtomicOrdering::Relaxed);
+        assert!(
+            read < data.len() / 2,
+            "{} of {} bytes read",
+            read,
+            data.len()
+        );
+
+        // servers without range support send the whole file
+        let sql = format!(
+            "select name from {}/plain.parquet where id > 140000",
+            server.uri()
+        );
+        assert_eq!((10_000, 1), crate::query(sql).await.unwrap().shape());
+    }
+}
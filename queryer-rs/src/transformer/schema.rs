@@ -0,0 +1,193 @@
+use anyhow::{anyhow, Context, Result};
+use polars::prelude::*;
+
+/// The type of a column given by the user, dates and times are read as text and
+/// parsed with `format`, e.g. `%d/%m/%Y`, detected if not given
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ColumnType {
+    pub(crate) name: String,
+    pub(crate) dtype: DataType,
+    pub(crate) format: Option<String>,
+}
+
+impl ColumnType {
+    /// The type to read the column as, text for dates and times
+    pub(crate) fn read_as(&self) -> DataType {
+        match self.dtype {
+            DataType::Date | DataType::Datetime(_, _) => DataType::Utf8,
+            _ => self.dtype.clone(),
+        }
+    }
+}
+
+/// Parse columns given as `name: type`, e.g. `id: int64, day: date(%d/%m/%Y)`.
+/// Types are `int8` to `int64`, `uint8` to `uint64`, `float32`, `float64`, `bool`,
+/// `utf8`, `date` and `datetime`, the last two with an optional format.
+pub(crate) fn parse_schema(spec: &str) -> Result<Vec<ColumnType>> {
+    let mut columns = Vec::new();
+    for column in split_columns(spec).into_iter().map(str::trim) {
+        if column.is_empty() {
+            continue;
+        }
+        let (name, ty) = column
+            .split_once(':')
+            .ok_or(anyhow!("column {} must look like name: type", column))?;
+        let ty = ty.trim();
+        let (ty, format) = match ty.split_once('(') {
+            Some((ty, format)) => {
+                let format = format
+                    .strip_suffix(')')
+                    .ok_or(anyhow!("unclosed ( in type {}", ty))?;
+                (ty.trim(), Some(format.to_string()))
+            }
+            None => (ty, None),
+        };
+        let dtype = dtype(ty)?;
+        if format.is_some() && !matches!(dtype, DataType::Date | DataType::Datetime(_, _)) {
+            return Err(anyhow!(
+                "only dates and datetimes take a format, not {}",
+                ty
+            ));
+        }
+        columns.push(ColumnType {
+            name: name.trim().to_string(),
+            dtype,
+            format,
+        });
+    }
+    Ok(columns)
+}
+
+/// Split at the commas outside of formats, which may hold commas themselves
+fn split_columns(spec: &str) -> Vec<&str> {
+    let mut columns = Vec::new();
+    let (mut depth, mut start) = (0, 0);
+    for (i, c) in spec.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                columns.push(&spec[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    columns.push(&spec[start..]);
+    columns
+}
+
+fn dtype(name: &str) -> Result<DataType> {
+    let dtype = match name.to_lowercase().as_str() {
+        "int8" => DataType::Int8,
+        "int16" => DataType::Int16,
+        "int32" => DataType::Int32,
+        "int64" | "int" | "integer" | "bigint" => DataType::Int64,
+        "uint8" => DataType::UInt8,
+        "uint16" => DataType::UInt16,
+        "uint32" => DataType::UInt32,
+        "uint64" => DataType::UInt64,
+        "float32" | "real" => DataType::Float32,
+        "float64" | "float" | "double" => DataType::Float64,
+        "bool" | "boolean" => DataType::Boolean,
+        "utf8" | "str" | "string" | "text" => DataType::Utf8,
+        "date" => DataType::Date,
+        "datetime" | "timestamp" => DataType::Datetime(TimeUnit::Milliseconds, None),
+        _ => return Err(anyhow!("unknown type {}", name)),
+    };
+    Ok(dtype)
+}
+
+/// Replace the `null_values` of text columns by nulls and give the columns of
+/// `schema` their type. Values which don't fit the type are an error, except dates
+/// and datetimes which are null if they don't parse.
+pub(crate) fn apply_schema(
+    mut df: DataFrame,
+    schema: &[ColumnType],
+    null_values: &[String],
+) -> Result<DataFrame> {
+    if !null_values.is_empty() {
+        let names: Vec<_> = df
+            .get_columns()
+            .iter()
+            .filter(|s| s.dtype() == &DataType::Utf8)
+            .map(|s| s.name().to_string())
+            .collect();
+        for name in names {
+            df.apply(&name, |s| {
+                s.utf8()
+                    .map(|ca| {
+                        ca.into_iter()
+                            .map(|v| v.filter(|v| !null_values.iter().any(|n| n == v)))
+                            .collect::<Utf8Chunked>()
+                            .into_series()
+                    })
+                    .unwrap_or_else(|_| s.clone())
+            })?;
+        }
+    }
+
+    for column in schema {
+        let s = df
+            .column(&column.name)
+            .with_context(|| format!("column {} of the schema not found", column.name))?;
+        if s.dtype() == &column.dtype {
+            continue;
+        }
+        let mut typed = match (&column.dtype, s.dtype()) {
+            (DataType::Date, DataType::Utf8) => s
+                .utf8()?
+                .as_date(column.format.as_deref(), false)?
+                .into_series(),
+            (DataType::Datetime(unit, _), DataType::Utf8) => s
+                .utf8()?
+                .as_datetime(column.format.as_deref(), *unit, false, false)?
+                .into_series(),
+            (dtype, _) => s
+                .strict_cast(dtype)
+                .with_context(|| format!("column {} can't be read as {}", column.name, dtype))?,
+        };
+        typed.rename(&column.name);
+        df.replace(&column.name, typed)?;
+    }
+    Ok(df)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_schema_should_work() {
+        let schema = parse_schema("id: int64, day:date(%b %d, %Y),amount : float,").unwrap();
+        assert_eq!(3, schema.len());
+        assert_eq!(DataType::Int64, schema[0].dtype);
+        assert_eq!(Some("%b %d, %Y"), schema[1].format.as_deref());
+        assert_eq!(DataType::Utf8, schema[1].read_as());
+        assert_eq!("amount", schema[2].name);
+        assert!(parse_schema("id").is_err());
+        assert!(parse_schema("id: decimal").is_err());
+        assert!(parse_schema("id: int(%d)").is_err());
+    }
+
+    #[test]
+    fn apply_schema_should_cast_and_parse() {
+        let df = df!(
+            "id" => ["1", "NA", "3"],
+            "day" => ["02/01/2023", "n/a", "04/01/2023"],
+            "x" => [1i64, 2, 3]
+        )
+        .unwrap();
+        let schema = parse_schema("id: uint32, day: date(%d/%m/%Y), x: float64").unwrap();
+        let null_values = vec!["NA".to_string(), "n/a".to_string()];
+        let df = apply_schema(df, &schema, &null_values).unwrap();
+        assert_eq!(&DataType::UInt32, df.column("id").unwrap().dtype());
+        assert_eq!(&DataType::Date, df.column("day").unwrap().dtype());
+        assert_eq!(&DataType::Float64, df.column("x").unwrap().dtype());
+        assert_eq!(1, df.column("day").unwrap().null_count());
+
+        let df = df!("id" => ["1", "x"]).unwrap();
+        assert!(apply_schema(df.clone(), &parse_schema("id: int64").unwrap(), &[]).is_err());
+        assert!(apply_schema(df, &parse_schema("missing: int64").unwrap(), &[]).is_err());
+    }
+}
@@ -0,0 +1,290 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use bytes::Bytes;
+use indexmap::IndexMap;
+use quick_xml::{events::Event, name::QName, Reader};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::DataSet;
+
+use super::{is_integer, json_pointer, read_records};
+
+/// An element of the document, names are without namespace prefix
+#[derive(Debug, Default)]
+struct Element {
+    name: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<Element>,
+    text: String,
+}
+
+/// The fields of a record, in document order
+type Record = IndexMap<String, Node>;
+
+/// A JSON value whose objects keep the order of the document
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum Node {
+    Value(Value),
+    List(Vec<Node>),
+    Record(Record),
+}
+
+impl Node {
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            Node::Value(v) => v.as_str(),
+            _ => None,
+        }
+    }
+
+    fn is_null(&self) -> bool {
+        matches!(self, Node::Value(Value::Null))
+    }
+}
+
+/// Read the elements at `path` (e.g. `/feed/entry`) as rows, by default the children
+/// of the document element. Attributes and child elements become columns, elements
+/// with children or attributes struct columns and repeated elements list columns.
+pub(crate) fn read_xml(data: Bytes, path: Option<&str>) -> Result<DataSet> {
+    let document = parse(&data)?;
+    let records = match path {
+        Some(path) => {
+            let pointer = json_pointer(path)?;
+            let mut names = pointer.split('/').skip(1);
+            match names.next() {
+                Some(name) if name == document.name => {
+                    names.fold(vec![&document], |elements, name| {
+                        elements
+                            .into_iter()
+                            .flat_map(|e| e.children.iter().filter(|c| c.name == name))
+                            .collect()
+                    })
+                }
+                _ => return Err(anyhow!("{} not found in xml", path)),
+            }
+        }
+        None => document.children.iter().collect(),
+    };
+
+    let mut records: Vec<_> = records
+        .into_iter()
+        .map(|e| match to_node(e) {
+            Node::Record(record) => record,
+            node => Record::from_iter([("value".to_string(), node)]),
+        })
+        .collect();
+    type_columns(&mut records);
+    read_records(&records)
+}
+
+fn parse(data: &[u8]) -> Result<Element> {
+    let mut reader = Reader::from_reader(data);
+    reader.config_mut().trim_text(true);
+
+    let mut stack = vec![Element::default()];
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) => stack.push(element(&reader, e.name(), e.attributes())?),
+            Event::Empty(e) => {
+                let element = element(&reader, e.name(), e.attributes())?;
+                if let Some(parent) = stack.last_mut() {
+                    parent.children.push(element);
+                }
+            }
+            Event::End(_) => {
+                let element = stack.pop().ok_or(anyhow!("unbalanced xml"))?;
+                let parent = stack.last_mut().ok_or(anyhow!("unbalanced xml"))?;
+                parent.children.push(element);
+            }
+            Event::Text(e) => {
+                if let Some(element) = stack.last_mut() {
+                    element.text.push_str(&e.unescape()?);
+                }
+            }
+            Event::CData(e) => {
+                if let Some(element) = stack.last_mut() {
+                    element.text.push_str(&String::from_utf8_lossy(&e));
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    match (stack.pop(), stack.is_empty()) {
+        (Some(document), true) => document
+            .children
+            .into_iter()
+            .next()
+            .ok_or(anyhow!("xml has no document element")),
+        _ => Err(anyhow!("xml ends in an open element")),
+    }
+}
+
+fn element(
+    reader: &Reader<&[u8]>,
+    name: QName,
+    attributes: quick_xml::events::attributes::Attributes,
+) -> Result<Element> {
+    let mut element = Element {
+        name: String::from_utf8_lossy(name.local_name().as_ref()).into_owned(),
+        ..Default::default()
+    };
+    for attribute in attributes {
+        let attribute = attribute?;
+        // namespace declarations are no data
+        if attribute.key.as_namespace_binding().is_some() {
+            continue;
+        }
+        element.attributes.push((
+            String::from_utf8_lossy(attribute.key.local_name().as_ref()).into_owned(),
+            attribute
+                .decode_and_unescape_value(reader.decoder())?
+                .into_owned(),
+        ));
+    }
+    Ok(element)
+}
+
+fn to_node(element: &Element) -> Node {
+    if element.children.is_empty() && element.attributes.is_empty() {
+        return match element.text.is_empty() {
+            true => Node::Value(Value::Null),
+            false => Node::Value(Value::String(element.text.clone())),
+        };
+    }
+
+    let mut record = Record::new();
+    for (name, value) in &element.attributes {
+        record.insert(name.clone(), Node::Value(Value::String(value.clone())));
+    }
+    if !element.text.is_empty() {
+        record.insert(
+            "value".to_string(),
+            Node::Value(Value::String(element.text.clone())),
+        );
+    }
+    let mut counts = HashMap::new();
+    for child in &element.children {
+        *counts.entry(child.name.as_str()).or_insert(0) += 1;
+    }
+    for child in &element.children {
+        let node = to_node(child);
+        match record.get_mut(&child.name) {
+            Some(Node::List(nodes)) => nodes.push(node),
+            _ if counts[child.name.as_str()] > 1 => {
+                record.insert(child.name.clone(), Node::List(vec![node]));
+            }
+            _ => {
+                record.insert(child.name.clone(), node);
+            }
+        }
+    }
+    Node::Record(record)
+}
+
+/// XML only holds text, turn the values of a top level column into numbers or
+/// booleans if all of them are. Columns repeated in some record are lists in all.
+fn type_columns(records: &mut [Record]) {
+    let mut names: Vec<String> = Vec::new();
+    for name in records.iter().flat_map(|r| r.keys()) {
+        if !names.contains(name) {
+            names.push(name.clone());
+        }
+    }
+
+    for name in names {
+        let mut values = records.iter_mut().filter_map(|r| r.get_mut(&name));
+        if values.any(|v| matches!(v, Node::List(_))) {
+            for value in records.iter_mut().filter_map(|r| r.get_mut(&name)) {
+                if !matches!(value, Node::List(_)) && !value.is_null() {
+                    let node = std::mem::replace(value, Node::Value(Value::Null));
+                    *value = Node::List(vec![node]);
+                }
+            }
+            continue;
+        }
+
+        let texts = || {
+            records
+                .iter()
+                .filter_map(|r| r.get(&name))
+                .filter(|v| !v.is_null())
+        };
+        let convert: fn(&str) -> Option<Value> =
+            if texts().all(|v| v.as_str().is_some_and(is_integer)) {
+                |s| s.parse::<i64>().ok().map(Value::from)
+            } else if texts().all(|v| v.as_str().and_then(|s| s.parse::<f64>().ok()).is_some()) {
+                |s| s.parse::<f64>().ok().map(Value::from)
+            } else if texts().all(|v| matches!(v.as_str(), Some("true" | "false"))) {
+                |s| Some(Value::Bool(s == "true"))
+            } else {
+                continue;
+            };
+
+        for value in records.iter_mut().filter_map(|r| r.get_mut(&name)) {
+            if let Some(converted) = value.as_str().and_then(convert) {
+                *value = Node::Value(converted);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use polars::prelude::DataType;
+
+    use super::*;
+
+    const FEED: &str = r#"<?xml version="1.0"?>
+        <feed xmlns="http://www.w3.org/2005/Atom" xmlns:x="urn:x">
+          <title>ignored</title>
+          <entry id="1">
+            <title>first &amp; best</title>
+            <x:score>1.5</x:score>
+            <author><name>a</name><zip>007</zip></author>
+            <tag>x</tag><tag>y</tag>
+          </entry>
+          <entry id="2">
+            <title><![CDATA[second <b>]]></title>
+            <score/>
+            <tag>z</tag>
+          </entry>
+        </feed>"#;
+
+    #[test]
+    fn read_xml_should_select_records() {
+        let ds = read_xml(FEED.into(), Some("/feed/entry")).unwrap();
+        assert_eq!(
+            vec!["id", "title", "score", "author", "tag"],
+            ds.get_column_names()
+        );
+        assert_eq!(2, ds.height());
+        assert_eq!(&DataType::Int64, ds.column("id").unwrap().dtype());
+        assert_eq!(&DataType::Float64, ds.column("score").unwrap().dtype());
+        assert!(matches!(
+            ds.column("author").unwrap().dtype(),
+            DataType::Struct(_)
+        ));
+        assert!(matches!(
+            ds.column("tag").unwrap().dtype(),
+            DataType::List(_)
+        ));
+        let titles: Vec<_> = ds
+            .column("title")
+            .unwrap()
+            .utf8()
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(vec![Some("first & best"), Some("second <b>")], titles);
+
+        // by default, the children of the document element are rows
+        assert_eq!(3, read_xml(FEED.into(), None).unwrap().height());
+        assert!(read_xml(FEED.into(), Some("/rss/item")).is_err());
+        assert!(read_xml("<a><b></a>".into(), None).is_err());
+    }
+}
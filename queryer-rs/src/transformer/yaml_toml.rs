@@ -0,0 +1,208 @@
+use anyhow::{anyhow, Result};
+use bytes::Bytes;
+use indexmap::IndexMap;
+use serde::Deserialize;
+
+use crate::{document::Document, DataSet};
+
+use super::{json_pointer, read_records};
+
+/// Read a YAML document, the rows are the mappings of the sequence at `root`.
+/// The records of the documents of a `---` separated stream are concatenated, a
+/// document without a sequence of records is a record itself.
+pub(crate) fn read_yaml(data: Bytes, root: Option<&str>) -> Result<DataSet> {
+    let documents = serde_yaml::Deserializer::from_slice(&data)
+        .map(Document::deserialize)
+        .collect::<Result<Vec<_>, _>>()?;
+    if documents.len() == 1 {
+        let document = documents.into_iter().next().unwrap_or_default();
+        return read_records(&select_records(document, root)?);
+    }
+
+    let mut records = Vec::new();
+    for document in documents {
+        match (root, document) {
+            (None, Document::Null) => {}
+            (None, document) if !has_records(&document) => records.push(document),
+            (root, document) => records.extend(select_records(document, root)?),
+        }
+    }
+    read_records(&single_column(records))
+}
+
+/// Read a TOML document, the rows are the tables of the array at `root`
+pub(crate) fn read_toml(data: Bytes, root: Option<&str>) -> Result<DataSet> {
+    let text = std::str::from_utf8(&data)?;
+    let document: toml::Table = toml::from_str(text)?;
+    read_records(&select_records(
+        from_toml(toml::Value::Table(document)),
+        root,
+    )?)
+}
+
+/// Pick the records at `root`, a pointer (`/inventory/services`) or a key
+/// (`services`). Without a root the document itself must be a sequence, or a
+/// mapping with a single sequence of mappings.
+fn select_records(mut document: Document, root: Option<&str>) -> Result<Vec<Document>> {
+    let records = match root {
+        Some(root) => {
+            let pointer = match root.starts_with(['/', '$']) {
+                true => json_pointer(root)?,
+                false => format!("/{}", root.replace('~', "~0").replace('/', "~1")),
+            };
+            document
+                .pointer_mut(&pointer)
+                .map(Document::take)
+                .ok_or(anyhow!("{} not found in document", root))?
+        }
+        None => match document {
+            Document::Array(_) => document,
+            Document::Object(map) => {
+                let mut sequences = map.into_iter().filter(|(_, v)| is_records(v));
+                match (sequences.next(), sequences.next()) {
+                    (Some((_, records)), None) => records,
+                    (Some((a, _)), Some((b, _))) => {
+                        return Err(anyhow!(
+                            "document has sequences {} and {}, pick a root",
+                            a,
+                            b
+                        ))
+                    }
+                    _ => return Err(anyhow!("document has no sequence of records")),
+                }
+            }
+            _ => return Err(anyhow!("document has no sequence of records")),
+        },
+    };
+
+    let records = match records {
+        Document::Array(records) => records,
+        Document::Object(_) => vec![records],
+        _ => {
+            return Err(anyhow!(
+                "{} is not a sequence of records",
+                root.unwrap_or("document")
+            ))
+        }
+    };
+    Ok(single_column(records))
+}
+
+/// A sequence of scalars is a single column
+fn single_column(records: Vec<Document>) -> Vec<Document> {
+    records
+        .into_iter()
+        .map(|r| match r {
+            Document::Object(_) => r,
+            value => Document::Object(IndexMap::from_iter([("value".to_string(), value)])),
+        })
+        .collect()
+}
+
+/// Whether `select_records` finds records in `document` without a root
+fn has_records(document: &Document) -> bool {
+    match document {
+        Document::Array(_) => true,
+        Document::Object(map) => map.values().any(is_records),
+        _ => false,
+    }
+}
+
+fn is_records(value: &Document) -> bool {
+    match value {
+        Document::Array(values) => values.first().is_some_and(Document::is_object),
+        _ => false,
+    }
+}
+
+/// TOML dates and times are kept as their text, `2023-01-02` or `07:32:00`
+fn from_toml(value: toml::Value) -> Document {
+    match value {
+        toml::Value::String(s) => Document::String(s),
+        toml::Value::Integer(i) => Document::from(i),
+        toml::Value::Float(f) => Document::from(f),
+        toml::Value::Boolean(b) => Document::Bool(b),
+        toml::Value::Datetime(d) => Document::String(d.to_string()),
+        toml::Value::Array(values) => Document::Array(values.into_iter().map(from_toml).collect()),
+        toml::Value::Table(table) => {
+            Document::Object(table.into_iter().map(|(k, v)| (k, from_toml(v))).collect())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use polars::prelude::DataType;
+
+    use super::*;
+
+    #[test]
+    fn read_yaml_should_work() {
+        let data = "- name: api\n  port: 8080\n  tags: [web]\n- name: db\n  port: 5432\n  enabled: false\n";
+        let ds = read_yaml(data.into(), None).unwrap();
+        assert_eq!(
+            vec!["name", "port", "tags", "enabled"],
+            ds.get_column_names()
+        );
+        assert_eq!(&DataType::Int64, ds.column("port").unwrap().dtype());
+        assert_eq!(&DataType::Boolean, ds.column("enabled").unwrap().dtype());
+
+        // columns are in the order of the keys
+        let data = "- zone: eu\n  name: api\n  active: true\n";
+        let ds = read_yaml(data.into(), None).unwrap();
+        assert_eq!(vec!["zone", "name", "active"], ds.get_column_names());
+
+        let data = "version: 2\nservices:\n  - name: api\n  - name: db\n";
+        assert_eq!((2, 1), read_yaml(data.into(), None).unwrap().shape());
+        assert_eq!(
+            (2, 1),
+            read_yaml(data.into(), Some("services")).unwrap().shape()
+        );
+        assert!(read_yaml(data.into(), Some("/missing")).is_err());
+        assert!(read_yaml("a: 1".into(), None).is_err());
+
+        // documents of a stream are concatenated
+        let data = "---\nname: api\nport: 8080\n---\nname: db\nport: 5432\n";
+        assert_eq!((2, 2), read_yaml(data.into(), None).unwrap().shape());
+        let data = "version: 1\nservices:\n  - name: api\n---\nservices:\n  - name: db\n";
+        let ds = read_yaml(data.into(), None).unwrap();
+        assert_eq!(vec!["name"], ds.get_column_names());
+        assert_eq!(2, ds.height());
+        let data = "services:\n  - name: api\n---\nservices:\n  - name: db\n  - name: mq\n";
+        let ds = read_yaml(data.into(), Some("services")).unwrap();
+        assert_eq!((3, 1), ds.shape());
+    }
+
+    #[test]
+    fn read_toml_should_work() {
+        let data = r#"
+            title = "flags"
+
+            [[flag]]
+            name = "dark_mode"
+            enabled = true
+            since = 2023-01-02
+
+            [[flag]]
+            name = "beta"
+            enabled = false
+            rollout = 0.5
+        "#;
+        let ds = read_toml(data.into(), None).unwrap();
+        assert_eq!(
+            vec!["name", "enabled", "since", "rollout"],
+            ds.get_column_names()
+        );
+        assert_eq!(&DataType::Boolean, ds.column("enabled").unwrap().dtype());
+        let since: Vec<_> = ds
+            .column("since")
+            .unwrap()
+            .utf8()
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(vec![Some("2023-01-02"), None], since);
+        assert_eq!(2, read_toml(data.into(), Some("/flag")).unwrap().height());
+        assert!(read_toml(data.into(), Some("title")).is_err());
+    }
+}